@@ -0,0 +1,374 @@
+use crate::main_camera::MainCamera;
+use crate::player::PLAYER_CENTER;
+use crate::replay::Playback;
+use crate::user_data;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+mod bindings;
+
+pub use bindings::{Action, Binding, Bindings};
+
+/// How far from the player keyboard and gamepad aiming put the aim point
+const AIM_REACH: f32 = 5.0;
+/// Radians a second the aim turns while a turn key is held
+const TURN_RATE: f32 = 2.5;
+/// Stick deflection below this leaves the aim where it is
+const DEAD_ZONE: f32 = 0.3;
+/// Keys picking weapon slots, in order, which can't be rebound
+const WEAPON_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ActionSet {
+    /// Input sources writing into `Actions`
+    Read,
+    /// Runs after every source but before the actions are consumed
+    Observe,
+}
+
+/// What the player asked for this frame, whatever device it came from. The
+/// backends here write it in `ActionSet::Read`, and so can anything else, e.g.
+/// [`crate::headless::SimulatedInput`], a replay or a test. One-shot actions
+/// stay set until whatever acts on them clears them
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Actions {
+    /// World point being aimed at
+    pub aim: Vec2,
+    /// The trigger went down, start charging a shot
+    pub charge: bool,
+    /// The trigger came back up, let the shot go
+    pub fire: bool,
+    pub pause: bool,
+    pub switch_weapon: Option<WeaponSwitch>,
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        Self {
+            aim: PLAYER_CENTER + Vec2::Y,
+            charge: false,
+            fire: false,
+            pause: false,
+            switch_weapon: None,
+        }
+    }
+}
+
+impl Actions {
+    /// Unit vector from the player towards `aim`, straight up when it's on the player
+    pub fn direction(&self) -> Vec2 {
+        (self.aim - PLAYER_CENTER)
+            .try_normalize()
+            .unwrap_or(Vec2::Y)
+    }
+
+    /// Drops every one-shot action, keeping the aim
+    pub fn clear(&mut self) {
+        *self = Self {
+            aim: self.aim,
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeaponSwitch {
+    Slot(usize),
+    Next,
+    Previous,
+}
+
+impl WeaponSwitch {
+    /// The slot this lands on out of `count`, stepping from `current` and
+    /// wrapping round at either end
+    pub fn resolve(self, current: usize, count: usize) -> usize {
+        match self {
+            WeaponSwitch::Slot(slot) => slot,
+            WeaponSwitch::Next => (current + 1) % count,
+            WeaponSwitch::Previous => (current + count - 1) % count,
+        }
+    }
+}
+
+/// Every device a binding can be read from
+#[derive(SystemParam)]
+pub(crate) struct DeviceButtons<'w> {
+    pub keys: Res<'w, ButtonInput<KeyCode>>,
+    pub mouse: Res<'w, ButtonInput<MouseButton>>,
+    pub gamepads: Res<'w, Gamepads>,
+    pub pads: Res<'w, ButtonInput<GamepadButton>>,
+}
+
+/// Reads the saved `Bindings`, unless some were inserted before it's added
+pub struct ActionsPlugin {
+    /// Leave out the device backends and the saved bindings, something else
+    /// drives `Actions`
+    pub headless: bool,
+}
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Actions>()
+            .configure_sets(Update, (ActionSet::Read, ActionSet::Observe).chain())
+            .add_systems(OnEnter(GameState::Playing), clear_actions)
+            .add_systems(
+                Update,
+                pause
+                    .after(ActionSet::Observe)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<Playback>)),
+            );
+
+        if !self.headless {
+            if !app.world.contains_resource::<Bindings>() {
+                let bindings: Bindings = user_data::load(&Bindings::config_path(), "controls");
+                app.insert_resource(bindings);
+            }
+
+            app.add_systems(
+                Update,
+                (mouse.run_if(in_state(GameState::Playing)), buttons, sticks)
+                    .in_set(ActionSet::Read)
+                    .run_if(not(in_state(GameState::Loading)))
+                    .run_if(not(resource_exists::<Playback>)),
+            );
+        }
+    }
+}
+
+/// Nothing pressed on the way in, e.g. in the menu, carries over into the run
+fn clear_actions(mut actions: ResMut<Actions>) {
+    actions.clear();
+}
+
+/// Freezes the run's clock, which holds back everything gated on `running`
+fn pause(mut actions: ResMut<Actions>, mut time: ResMut<Time<Virtual>>) {
+    if !std::mem::take(&mut actions.pause) {
+        return;
+    }
+
+    if time.is_paused() {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+/// Aims at the cursor and scrolling down steps to the next weapon. Only during
+/// a run, the menu has buttons to click
+fn mouse(
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut cursor: EventReader<CursorMoved>,
+    mut wheel: EventReader<MouseWheel>,
+    mut actions: ResMut<Actions>,
+) {
+    if let Some(moved) = cursor.read().last() {
+        if let Ok((camera, transform)) = camera.get_single() {
+            if let Some(point) = camera.viewport_to_world_2d(transform, moved.position) {
+                actions.aim = point;
+            }
+        }
+    }
+
+    let scroll: f32 = wheel.read().map(|e| e.y).sum();
+    if scroll < 0.0 {
+        actions.switch_weapon = Some(WeaponSwitch::Next);
+    } else if scroll > 0.0 {
+        actions.switch_weapon = Some(WeaponSwitch::Previous);
+    }
+}
+
+/// Everything bound in `Bindings`, plus the number keys picking weapon slots.
+/// Mouse buttons only count during a run, the menu has buttons to click
+fn buttons(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    bindings: Res<Bindings>,
+    devices: DeviceButtons,
+    mut actions: ResMut<Actions>,
+) {
+    let playing = *state.get() == GameState::Playing;
+    let bound = |action: Action| {
+        bindings
+            .get(action)
+            .iter()
+            .copied()
+            .filter(move |binding| playing || !matches!(binding, Binding::Mouse(_)))
+    };
+
+    let mut turn = 0.0;
+    if bound(Action::TurnLeft).any(|b| b.pressed(&devices)) {
+        turn += 1.0;
+    }
+    if bound(Action::TurnRight).any(|b| b.pressed(&devices)) {
+        turn -= 1.0;
+    }
+    if turn != 0.0 {
+        let direction =
+            Vec2::from_angle(turn * TURN_RATE * time.delta_seconds()).rotate(actions.direction());
+        actions.aim = PLAYER_CENTER + direction * AIM_REACH;
+    }
+
+    if bound(Action::Trigger).any(|b| b.just_pressed(&devices)) {
+        actions.charge = true;
+    } else if bound(Action::Trigger).any(|b| b.just_released(&devices)) {
+        actions.fire = true;
+    }
+
+    if bound(Action::Pause).any(|b| b.just_pressed(&devices)) {
+        actions.pause = true;
+    }
+
+    if bound(Action::PreviousWeapon).any(|b| b.just_pressed(&devices)) {
+        actions.switch_weapon = Some(WeaponSwitch::Previous);
+    } else if bound(Action::NextWeapon).any(|b| b.just_pressed(&devices)) {
+        actions.switch_weapon = Some(WeaponSwitch::Next);
+    }
+    if let Some(slot) = WEAPON_KEYS
+        .iter()
+        .position(|key| devices.keys.just_pressed(*key))
+    {
+        actions.switch_weapon = Some(WeaponSwitch::Slot(slot));
+    }
+}
+
+/// Either stick aims
+fn sticks(gamepads: Res<Gamepads>, axes: Res<Axis<GamepadAxis>>, mut actions: ResMut<Actions>) {
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let stick = [
+            (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        ]
+        .into_iter()
+        .map(|(x, y)| Vec2::new(axis(x), axis(y)))
+        .find(|stick| stick.length() > DEAD_ZONE);
+        if let Some(stick) = stick {
+            actions.aim = PLAYER_CENTER + stick.normalize() * AIM_REACH;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::Hash;
+
+    /// The actions layer on its own with the default bindings, no saved ones,
+    /// started in `state`. The devices are bare resources for the tests to
+    /// press, there's no input backend clearing them
+    fn app(state: GameState) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_state(state)
+            .insert_resource(Bindings::default())
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .add_event::<CursorMoved>()
+            .add_event::<MouseWheel>()
+            .add_plugins(ActionsPlugin { headless: false });
+        app.update();
+        app
+    }
+
+    fn press<T: Copy + Eq + Hash + Send + Sync + 'static>(app: &mut App, input: T) {
+        app.world.resource_mut::<ButtonInput<T>>().press(input);
+        app.update();
+        app.world.resource_mut::<ButtonInput<T>>().clear();
+    }
+
+    fn release<T: Copy + Eq + Hash + Send + Sync + 'static>(app: &mut App, input: T) {
+        app.world.resource_mut::<ButtonInput<T>>().release(input);
+        app.update();
+        app.world.resource_mut::<ButtonInput<T>>().clear();
+    }
+
+    fn actions(app: &App) -> &Actions {
+        app.world.resource::<Actions>()
+    }
+
+    #[test]
+    fn trigger_charges_then_fires() {
+        let mut app = app(GameState::Playing);
+        press(&mut app, KeyCode::Space);
+        assert!(actions(&app).charge);
+        assert!(!actions(&app).fire);
+
+        release(&mut app, KeyCode::Space);
+        assert!(actions(&app).fire);
+    }
+
+    #[test]
+    fn rebinding_replaces_the_old_key() {
+        let mut app = app(GameState::Playing);
+        app.world
+            .resource_mut::<Bindings>()
+            .rebind(Action::Trigger, Binding::Key(KeyCode::KeyF));
+
+        press(&mut app, KeyCode::Space);
+        assert!(!actions(&app).charge);
+        press(&mut app, KeyCode::KeyF);
+        assert!(actions(&app).charge);
+    }
+
+    #[test]
+    fn mouse_buttons_only_count_during_a_run() {
+        let mut menu = app(GameState::Menu);
+        press(&mut menu, MouseButton::Left);
+        assert!(!actions(&menu).charge);
+
+        let mut run = app(GameState::Playing);
+        press(&mut run, MouseButton::Left);
+        assert!(actions(&run).charge);
+    }
+
+    #[test]
+    fn weapon_keys_switch() {
+        let mut app = app(GameState::Playing);
+        press(&mut app, KeyCode::KeyE);
+        assert_eq!(actions(&app).switch_weapon, Some(WeaponSwitch::Next));
+
+        press(&mut app, KeyCode::Digit3);
+        assert_eq!(actions(&app).switch_weapon, Some(WeaponSwitch::Slot(2)));
+    }
+
+    #[test]
+    fn pause_key_toggles_the_clock() {
+        let mut app = app(GameState::Playing);
+        press(&mut app, KeyCode::Escape);
+        assert!(app.world.resource::<Time<Virtual>>().is_paused());
+
+        release(&mut app, KeyCode::Escape);
+        press(&mut app, KeyCode::Escape);
+        assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+    }
+
+    #[test]
+    fn weapon_switch_wraps_round() {
+        assert_eq!(WeaponSwitch::Next.resolve(0, 3), 1);
+        assert_eq!(WeaponSwitch::Next.resolve(2, 3), 0);
+        assert_eq!(WeaponSwitch::Previous.resolve(0, 3), 2);
+        assert_eq!(WeaponSwitch::Previous.resolve(1, 3), 0);
+        assert_eq!(WeaponSwitch::Next.resolve(0, 1), 0);
+        assert_eq!(WeaponSwitch::Previous.resolve(0, 1), 0);
+        assert_eq!(WeaponSwitch::Slot(2).resolve(0, 3), 2);
+    }
+}
@@ -0,0 +1,258 @@
+use super::{DeviceButtons, WEAPON_KEYS};
+use crate::user_data;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const FILE_NAME: &str = "controls.toml";
+
+/// The actions that can be rebound. Aiming with the mouse or a stick, and the
+/// number keys picking weapon slots, can't be
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Charges on press, fires on release
+    Trigger,
+    TurnLeft,
+    TurnRight,
+    PreviousWeapon,
+    NextWeapon,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::Trigger,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::PreviousWeapon,
+        Action::NextWeapon,
+        Action::Pause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Trigger => "Charge / fire",
+            Action::TurnLeft => "Aim left",
+            Action::TurnRight => "Aim right",
+            Action::PreviousWeapon => "Previous weapon",
+            Action::NextWeapon => "Next weapon",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+/// A button on any device, written the way it's shown, e.g. `"Space"`,
+/// `"Mouse Left"` or `"Pad South"`
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    fn same_device(self, other: Binding) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    /// Held down, any gamepad counts
+    pub(crate) fn pressed(self, buttons: &DeviceButtons) -> bool {
+        match self {
+            Binding::Key(key) => buttons.keys.pressed(key),
+            Binding::Mouse(button) => buttons.mouse.pressed(button),
+            Binding::Gamepad(button_type) => buttons.gamepads.iter().any(|gamepad| {
+                buttons
+                    .pads
+                    .pressed(GamepadButton::new(gamepad, button_type))
+            }),
+        }
+    }
+
+    pub(crate) fn just_pressed(self, buttons: &DeviceButtons) -> bool {
+        match self {
+            Binding::Key(key) => buttons.keys.just_pressed(key),
+            Binding::Mouse(button) => buttons.mouse.just_pressed(button),
+            Binding::Gamepad(button_type) => buttons.gamepads.iter().any(|gamepad| {
+                buttons
+                    .pads
+                    .just_pressed(GamepadButton::new(gamepad, button_type))
+            }),
+        }
+    }
+
+    pub(crate) fn just_released(self, buttons: &DeviceButtons) -> bool {
+        match self {
+            Binding::Key(key) => buttons.keys.just_released(key),
+            Binding::Mouse(button) => buttons.mouse.just_released(button),
+            Binding::Gamepad(button_type) => buttons.gamepads.iter().any(|gamepad| {
+                buttons
+                    .pads
+                    .just_released(GamepadButton::new(gamepad, button_type))
+            }),
+        }
+    }
+
+    /// The first button pressed this frame on any device, skipping ones that
+    /// can't be written down, e.g. `MouseButton::Other`
+    pub(crate) fn first_pressed(buttons: &DeviceButtons) -> Option<Binding> {
+        let keys = buttons.keys.get_just_pressed().map(|k| Binding::Key(*k));
+        let mouse = buttons.mouse.get_just_pressed().map(|b| Binding::Mouse(*b));
+        let pads = buttons
+            .pads
+            .get_just_pressed()
+            .map(|b| Binding::Gamepad(b.button_type));
+
+        keys.chain(mouse)
+            .chain(pads)
+            .find(|binding| binding.to_string().parse::<Binding>().is_ok())
+    }
+
+    /// Kept for something that can't be rebound
+    pub fn reserved(self) -> bool {
+        matches!(self, Binding::Key(key) if WEAPON_KEYS.contains(&key))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{}", key.variant_name()),
+            Binding::Mouse(button) => write!(f, "Mouse {}", button.variant_name()),
+            Binding::Gamepad(button_type) => write!(f, "Pad {}", button_type.variant_name()),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let binding = if let Some(button) = s.strip_prefix("Mouse ") {
+            variant(button).map(Binding::Mouse)
+        } else if let Some(button_type) = s.strip_prefix("Pad ") {
+            variant(button_type).map(Binding::Gamepad)
+        } else {
+            variant(s).map(Binding::Key)
+        };
+        binding.ok_or_else(|| format!("unknown button {s:?}"))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Looks up a field-less variant by name, the input types only derive serde
+/// behind a Bevy feature this game doesn't use
+fn variant<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+/// Every button bound to each action, saved to the user's config directory
+#[derive(Resource, serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Bindings {
+    pub trigger: Vec<Binding>,
+    pub turn_left: Vec<Binding>,
+    pub turn_right: Vec<Binding>,
+    pub previous_weapon: Vec<Binding>,
+    pub next_weapon: Vec<Binding>,
+    pub pause: Vec<Binding>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            trigger: vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButtonType::South),
+                Binding::Gamepad(GamepadButtonType::RightTrigger2),
+            ],
+            turn_left: vec![
+                Binding::Key(KeyCode::KeyA),
+                Binding::Key(KeyCode::ArrowLeft),
+                Binding::Gamepad(GamepadButtonType::DPadLeft),
+            ],
+            turn_right: vec![
+                Binding::Key(KeyCode::KeyD),
+                Binding::Key(KeyCode::ArrowRight),
+                Binding::Gamepad(GamepadButtonType::DPadRight),
+            ],
+            previous_weapon: vec![
+                Binding::Key(KeyCode::KeyQ),
+                Binding::Gamepad(GamepadButtonType::LeftTrigger),
+            ],
+            next_weapon: vec![
+                Binding::Key(KeyCode::KeyE),
+                Binding::Gamepad(GamepadButtonType::RightTrigger),
+            ],
+            pause: vec![
+                Binding::Key(KeyCode::Escape),
+                Binding::Key(KeyCode::KeyP),
+                Binding::Gamepad(GamepadButtonType::Start),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// In the user's config directory, see [`user_data::config_dir`]
+    pub fn config_path() -> PathBuf {
+        user_data::config_path(FILE_NAME)
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::Trigger => &self.trigger,
+            Action::TurnLeft => &self.turn_left,
+            Action::TurnRight => &self.turn_right,
+            Action::PreviousWeapon => &self.previous_weapon,
+            Action::NextWeapon => &self.next_weapon,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    fn get_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        match action {
+            Action::Trigger => &mut self.trigger,
+            Action::TurnLeft => &mut self.turn_left,
+            Action::TurnRight => &mut self.turn_right,
+            Action::PreviousWeapon => &mut self.previous_weapon,
+            Action::NextWeapon => &mut self.next_weapon,
+            Action::Pause => &mut self.pause,
+        }
+    }
+
+    /// The other action `binding` already belongs to, if any
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|other| *other != action && self.get(*other).contains(&binding))
+    }
+
+    /// Binds `binding` to `action` in place of whatever it had on the same device
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.get_mut(action);
+        bindings.retain(|b| !b.same_device(binding));
+        bindings.push(binding);
+    }
+
+    /// Writes to `config_path`, logging rather than failing
+    pub(crate) fn store(&self) {
+        user_data::save(&Bindings::config_path(), self, "controls");
+    }
+}
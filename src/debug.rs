@@ -2,6 +2,7 @@ use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin, LogDiagnost
 use bevy::prelude::*;
 
 use crate::loading::FontAssets;
+use crate::rng::GameSeed;
 use crate::GameState;
 
 /// Marker to find the container entity so we can show/hide the FPS counter
@@ -18,12 +19,12 @@ impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(FrameTimeDiagnosticsPlugin)
             .add_plugins(LogDiagnosticsPlugin::default())
-            .add_systems(OnEnter(GameState::Menu), setup_fps_counter)
+            .add_systems(OnExit(GameState::Loading), setup_fps_counter)
             .add_systems(Update, (fps_text_update_system, fps_counter_showhide));
     }
 }
 
-fn setup_fps_counter(mut commands: Commands, font_assets: Res<FontAssets>) {
+fn setup_fps_counter(mut commands: Commands, font_assets: Res<FontAssets>, seed: Res<GameSeed>) {
     // create our UI root node
     // this is the wrapper/container for the text
     let root = commands
@@ -76,6 +77,16 @@ fn setup_fps_counter(mut commands: Commands, font_assets: Res<FontAssets>) {
                             font: font_assets.fantasque_sans.clone(),
                         },
                     },
+                    // kept up to date by `fps_text_update_system`, a new run
+                    // can bring a new seed
+                    TextSection {
+                        value: format!("  Seed: {}", seed.0),
+                        style: TextStyle {
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                            font: font_assets.fantasque_sans.clone(),
+                        },
+                    },
                 ]),
                 ..Default::default()
             },
@@ -86,9 +97,14 @@ fn setup_fps_counter(mut commands: Commands, font_assets: Res<FontAssets>) {
 
 fn fps_text_update_system(
     diagnostics: Res<DiagnosticsStore>,
+    seed: Res<GameSeed>,
     mut query: Query<&mut Text, With<FpsText>>,
 ) {
     for mut text in &mut query {
+        if seed.is_changed() {
+            text.sections[2].value = format!("  Seed: {}", seed.0);
+        }
+
         // try to get a "smoothed" FPS value from Bevy
         if let Some(value) = diagnostics
             .get(&FrameTimeDiagnosticsPlugin::FPS)
@@ -0,0 +1,125 @@
+use crate::events::TimeTable;
+use crate::loading::GameData;
+use crate::GameState;
+use bevy::{prelude::*, reflect::TypePath, utils::HashMap};
+
+/// Spawn table used when a preset is missing or names one that isn't loaded
+const DEFAULT_SPAWN_TABLE: &str = "spawn-rates.time.toml";
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .add_systems(OnExit(GameState::Loading), apply_difficulty)
+            .add_systems(
+                Update,
+                apply_difficulty
+                    .run_if(in_state(GameState::Menu))
+                    .run_if(resource_changed::<Difficulty>),
+            );
+    }
+}
+
+/// The preset picked in the menu. Insert one before adding `GamePlugin` to
+/// start on something other than `Normal`
+#[derive(
+    Resource, serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Nightmare,
+    ];
+}
+
+/// Every preset, keyed by difficulty, e.g. `[hard]`
+#[derive(serde::Deserialize, Clone, TypePath, Asset)]
+pub struct DifficultyPresets {
+    #[serde(flatten)]
+    presets: HashMap<Difficulty, Preset>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)]
+pub struct Preset {
+    /// Asset path of the spawn table, it also has to be listed in `GameData`
+    pub spawn_rates: String,
+    pub enemy_health: f32,
+    /// Damage enemy projectiles deal to the player
+    pub projectile_damage: f32,
+    /// Divides the time between random spawns
+    pub spawn_speed: f32,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self {
+            spawn_rates: DEFAULT_SPAWN_TABLE.into(),
+            enemy_health: 1.0,
+            projectile_damage: 1.0,
+            spawn_speed: 1.0,
+        }
+    }
+}
+
+/// The selected preset, resolved against the loaded assets
+#[derive(Resource)]
+pub struct DifficultySettings {
+    pub difficulty: Difficulty,
+    pub preset: Preset,
+    pub spawn_rates: Handle<TimeTable>,
+}
+
+fn apply_difficulty(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    game_data: Res<GameData>,
+    presets: Res<Assets<DifficultyPresets>>,
+) {
+    let mut preset = presets
+        .get(&game_data.difficulty_presets)
+        .and_then(|presets| presets.presets.get(&*difficulty))
+        .cloned()
+        .unwrap_or_else(|| {
+            warn!("No preset for {:?}, using the defaults", *difficulty);
+            Preset::default()
+        });
+    if !(preset.spawn_speed > 0.0) {
+        warn!(
+            "{:?} spawn_speed is {}, it has to be above 0",
+            *difficulty, preset.spawn_speed
+        );
+        preset.spawn_speed = 1.0;
+    }
+
+    let spawn_rates = game_data
+        .spawn_tables
+        .get(&preset.spawn_rates)
+        .or_else(|| {
+            error!(
+                "{:?} uses spawn table {}, which isn't loaded",
+                *difficulty, preset.spawn_rates
+            );
+            game_data.spawn_tables.get(DEFAULT_SPAWN_TABLE)
+        })
+        .cloned()
+        .unwrap_or_default();
+
+    commands.insert_resource(DifficultySettings {
+        difficulty: *difficulty,
+        preset,
+        spawn_rates,
+    });
+}
@@ -1,25 +1,44 @@
+pub(crate) mod archetype;
+mod behemoth;
 mod climber;
+mod diver;
 pub(crate) mod enemy_projectile;
 mod giant;
 mod hopper;
 mod lurker;
+pub(crate) mod spawn_point;
+pub(crate) mod telegraph;
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 
+pub use crate::enemies::behemoth::Behemoth;
 pub use crate::enemies::giant::Giant;
+use crate::difficulty::DifficultySettings;
 use crate::events::EnemySpawnsChanged;
+use crate::loading::{GameData, TextureAssets};
 use crate::player::PlayerProjectile;
-use crate::{GameState, PhysicsLayers};
+use crate::rng::GameRng;
+use crate::waves::random_spawns;
+use crate::world::ArenaEntity;
+use crate::{running, GameState, PhysicsLayers};
 use benimator::FrameRate;
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_xpbd_2d::prelude::*;
 use rand::Rng;
 
+use self::archetype::{EnemyArchetype, EnemyArchetypes};
+use self::behemoth::{BehemothPlugin, BehemothSpawn};
 use self::climber::{ClimberPlugin, ClimberSpawn};
+use self::diver::{DiverPlugin, DiverSpawn};
 use self::enemy_projectile::ProjectilePlugin;
 use self::giant::{GiantPlugin, GiantSpawn};
 use self::hopper::{HopperPlugin, HopperSpawn};
 use self::lurker::{LurkerPlugin, LurkerSpawn};
+use self::spawn_point::{load_spawn_points, SpawnPoints};
+use self::telegraph::{
+    announce_arrivals, announce_spawns, tick_telegraphs, EnemySpawned, SpawnTelegraphed, Telegraph,
+};
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum Facing {
@@ -43,10 +62,123 @@ impl From<Facing> for bool {
     }
 }
 
+#[derive(
+    Component,
+    serde::Deserialize,
+    serde::Serialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EnemyKind {
+    Hopper,
+    Climber,
+    Lurker,
+    Diver,
+    Giant,
+    Behemoth,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 6] = [
+        EnemyKind::Hopper,
+        EnemyKind::Climber,
+        EnemyKind::Lurker,
+        EnemyKind::Diver,
+        EnemyKind::Giant,
+        EnemyKind::Behemoth,
+    ];
+
+    /// Moves by hopping, so its archetypes need a `hop` table
+    pub fn hops(self) -> bool {
+        matches!(
+            self,
+            EnemyKind::Hopper | EnemyKind::Giant | EnemyKind::Behemoth
+        )
+    }
+}
+
+/// Overrides for a spawn marker, anything left as `None` is rolled as usual.
+/// Carried over onto the enemy it spawns
+#[derive(Component, Clone, Default)]
+pub(crate) struct SpawnParams {
+    pub facing: Option<Facing>,
+    /// Archetype variant name, e.g. `"brute"`
+    pub variant: Option<String>,
+    /// Index of the scripted wave the enemy belongs to
+    pub wave: Option<usize>,
+    /// Named spawn point, e.g. `"sky"`
+    pub point: Option<String>,
+    /// Exact spawn position, settled when the spawn is telegraphed
+    pub position: Option<Vec2>,
+}
+
+pub(crate) fn roll_facing(params: Option<&SpawnParams>, rng: &mut impl Rng) -> Facing {
+    params.and_then(|p| p.facing).unwrap_or_else(|| {
+        if rng.gen_bool(0.5) {
+            Facing::Left
+        } else {
+            Facing::Right
+        }
+    })
+}
+
+/// Spawns the marker for `kind`, its spawn system builds the enemy once the
+/// telegraph runs out
+pub(crate) fn queue_spawn(commands: &mut Commands, kind: EnemyKind, params: SpawnParams) {
+    let mut marker = commands.spawn((params, Telegraph::new(kind), ArenaEntity));
+    match kind {
+        EnemyKind::Hopper => marker.insert(HopperSpawn),
+        EnemyKind::Climber => marker.insert(ClimberSpawn),
+        EnemyKind::Lurker => marker.insert(LurkerSpawn),
+        EnemyKind::Diver => marker.insert(DiverSpawn),
+        EnemyKind::Giant => marker.insert(GiantSpawn),
+        EnemyKind::Behemoth => marker.insert(BehemothSpawn),
+    };
+}
+
+/// What took an enemy's last point of health
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeathCause {
+    Projectile {
+        size: i32,
+    },
+    /// `chain` counts the deaths that led up to this explosion, starting at 1.
+    /// It's 0 for the player's own projectile blasts
+    Explosion {
+        power: i32,
+        chain: u32,
+    },
+    /// Ran into something, e.g. a diver hitting the ground
+    #[default]
+    Crash,
+}
+
+#[derive(Event)]
+pub struct EnemyDied {
+    pub entity: Entity,
+    pub kind: EnemyKind,
+    pub position: Vec2,
+    pub overkill: i32,
+    /// Base score for the kill, before any multipliers
+    pub points: u32,
+    pub cause: DeathCause,
+}
+
 #[derive(Component)]
 pub(crate) struct Enemy {
     pub health: i32,
     pub facing: Facing,
+    pub last_hit: DeathCause,
+    pub points: u32,
+    /// Share of the spawn budget it holds until it dies
+    pub cost: f32,
 }
 
 impl Default for Enemy {
@@ -54,13 +186,72 @@ impl Default for Enemy {
         Self {
             health: 1,
             facing: Facing::Left,
+            last_hit: DeathCause::default(),
+            points: 0,
+            cost: 0.0,
+        }
+    }
+}
+
+/// The explosion an enemy leaves behind, its power is the overkill times `scale`
+#[derive(Component, serde::Deserialize, Clone, Copy)]
+#[serde(default)]
+pub(crate) struct DeathExplosion {
+    pub scale: f32,
+    pub min_power: i32,
+    pub duration: f32,
+}
+
+impl Default for DeathExplosion {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            min_power: 0,
+            duration: 0.5,
         }
     }
 }
 
+/// What touching the player costs them, and what becomes of the enemy after
+#[derive(Component, serde::Deserialize, Clone, Copy)]
+#[serde(default)]
+pub(crate) struct ContactDamage {
+    pub damage: i32,
+    pub response: ContactResponse,
+    /// Impulse away from the player, only used with `ContactResponse::Knockback`
+    pub knockback: f32,
+}
+
+impl Default for ContactDamage {
+    fn default() -> Self {
+        Self {
+            damage: 1,
+            response: ContactResponse::Despawn,
+            knockback: 0.0,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ContactResponse {
+    Despawn,
+    Knockback,
+    /// Removes itself, e.g. projectiles
+    Nothing,
+}
+
+/// Hits weaker than `threshold` are shrugged off entirely
+#[derive(Component, Default)]
+pub(crate) struct Armor {
+    pub threshold: i32,
+}
+
 #[derive(Component, Default)]
 pub(crate) struct Explosion {
     pub power: i32,
+    /// Length of the chain of deaths this explosion belongs to
+    pub chain: u32,
     timer: Timer,
 }
 
@@ -74,6 +265,7 @@ struct ExplosionBundle {
     animation: ExplosionAnimation,
     animation_state: ExplosionAnimationState,
     explosion: Explosion,
+    arena: ArenaEntity,
 }
 
 impl Default for ExplosionBundle {
@@ -93,6 +285,7 @@ impl Default for ExplosionBundle {
             )),
             animation_state: Default::default(),
             explosion: Default::default(),
+            arena: ArenaEntity,
         }
     }
 }
@@ -108,12 +301,6 @@ struct ExplosionAnimationState(benimator::State);
 #[derive(Component)]
 struct Lurker;
 
-#[derive(Component)]
-struct Diver;
-
-#[derive(Component)]
-struct Behemoth;
-
 #[derive(Component, Default)]
 pub(crate) struct Hop {
     pub grounded: bool,
@@ -133,16 +320,42 @@ struct SpawnTimer {
     timer: Timer,
 }
 
-#[derive(Resource, serde::Deserialize, Clone, Default)]
+impl Default for SpawnTimer {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Resource, serde::Deserialize, serde::Serialize, Clone, Default)]
 pub struct SpawnRates {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_spawn_time: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_spawn_time: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub hopper: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub climber: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lurker: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub diver: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub giant: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub behemoth: Option<f32>,
+    /// Chance a random spawn comes from the left, 0.5 when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_left: Option<f32>,
+    /// Total `cost` of enemies allowed on the field before random spawns hold
+    /// back, unlimited when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<f32>,
+    /// Most of each kind alive at once, e.g. `[t.60.caps]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub caps: BTreeMap<EnemyKind, u32>,
 }
 
 impl SpawnRates {
@@ -154,42 +367,95 @@ impl SpawnRates {
             + self.giant.unwrap_or_default()
             + self.behemoth.unwrap_or_default()
     }
+
+    pub fn weight(&self, kind: EnemyKind) -> f32 {
+        match kind {
+            EnemyKind::Hopper => self.hopper,
+            EnemyKind::Climber => self.climber,
+            EnemyKind::Lurker => self.lurker,
+            EnemyKind::Diver => self.diver,
+            EnemyKind::Giant => self.giant,
+            EnemyKind::Behemoth => self.behemoth,
+        }
+        .unwrap_or_default()
+    }
+
+    /// The kind `roll`, between 0 and 1, lands on
+    fn pick(&self, mut roll: f32) -> Option<EnemyKind> {
+        for kind in EnemyKind::ALL {
+            let weight = self.weight(kind);
+            if roll <= weight {
+                return Some(kind);
+            }
+            roll -= weight;
+        }
+        None
+    }
 }
 
 pub struct EnemiesPlugin;
 
 impl Plugin for EnemiesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), clear_enemies)
+        app.add_event::<EnemyDied>()
+            .add_event::<SpawnTelegraphed>()
+            .add_event::<EnemySpawned>()
+            .add_systems(
+                OnEnter(GameState::Menu),
+                (load_archetypes, load_spawn_points).chain(),
+            )
+            .add_systems(OnEnter(GameState::Playing), reset_spawns)
             .add_systems(
                 Update,
                 (
                     update_enemy_spawns,
-                    enemy_spawner,
+                    enemy_spawner.run_if(random_spawns),
+                    (announce_spawns, tick_telegraphs)
+                        .chain()
+                        .after(enemy_spawner),
+                    announce_arrivals,
                     hop,
                     hop_grounding,
                     enemy_hits,
+                    enemy_death.after(enemy_hits),
                     explosion_cleanup,
                     explosion_animate,
                 )
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(running),
             )
+            .init_resource::<EnemyArchetypes>()
             .init_resource::<SpawnRates>()
-            .insert_resource(SpawnTimer {
-                timer: Timer::from_seconds(1.0, TimerMode::Repeating),
-            })
+            .init_resource::<SpawnTimer>()
+            .init_resource::<SpawnPoints>()
             .add_plugins(HopperPlugin)
             .add_plugins(ClimberPlugin)
             .add_plugins(LurkerPlugin)
+            .add_plugins(DiverPlugin)
             .add_plugins(GiantPlugin)
+            .add_plugins(BehemothPlugin)
             .add_plugins(ProjectilePlugin);
     }
 }
 
-fn clear_enemies(mut commands: Commands, query: Query<Entity, With<Enemy>>) {
-    for entity in query.iter() {
-        let _ = &commands.entity(entity).despawn_recursive();
+fn load_archetypes(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    archetypes_asset: Res<Assets<EnemyArchetypes>>,
+) {
+    let Some(archetypes) = archetypes_asset.get(&game_data.enemy_archetypes) else {
+        error!("No enemy archetypes loaded, no enemies will spawn");
+        return;
+    };
+    for error in archetypes.validate() {
+        error!("archetypes.enemies.toml: {error}");
     }
+    commands.insert_resource(archetypes.clone());
+}
+
+/// Every run starts from the top of the spawn table
+fn reset_spawns(mut spawn_rates: ResMut<SpawnRates>, mut spawn_timer: ResMut<SpawnTimer>) {
+    *spawn_rates = SpawnRates::default();
+    *spawn_timer = SpawnTimer::default();
 }
 
 fn update_enemy_spawns(
@@ -221,14 +487,26 @@ fn update_enemy_spawns(
         if let Some(val) = e.behemoth {
             spawn_rates.behemoth = Some(val);
         }
+        if let Some(val) = e.from_left {
+            spawn_rates.from_left = Some(val);
+        }
+        if let Some(val) = e.budget {
+            spawn_rates.budget = Some(val);
+        }
+        spawn_rates.caps.extend(e.caps.clone());
     }
 }
 
 fn enemy_spawner(
     time: Res<Time>,
     spawn_rates: Res<SpawnRates>,
+    difficulty: Res<DifficultySettings>,
+    archetypes: Res<EnemyArchetypes>,
+    enemies: Query<(&Enemy, &EnemyKind)>,
+    incoming: Query<&Telegraph>,
     mut commands: Commands,
     mut spawn_timer: ResMut<SpawnTimer>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     spawn_timer.timer.tick(time.delta());
 
@@ -238,39 +516,81 @@ fn enemy_spawner(
 
     let min_spawn_time = spawn_rates.min_spawn_time.unwrap_or_default();
     let max_spawn_time = spawn_rates.max_spawn_time.unwrap_or_default();
-    let dur = rand::thread_rng().gen_range(min_spawn_time..max_spawn_time);
+    let dur = game_rng.spawning.gen_range(min_spawn_time..=max_spawn_time)
+        / difficulty.preset.spawn_speed;
     spawn_timer.timer.set_duration(Duration::from_secs_f32(dur));
 
-    let mut rng = rand::thread_rng().gen_range(0f32..1f32);
+    let roll = game_rng.spawning.gen_range(0f32..1f32);
 
-    // Hopper
-    if rng <= spawn_rates.hopper.unwrap_or_default() {
-        // Hopper::spawn(commands, facing, start_x);
-        commands.spawn(HopperSpawn);
-        return;
-    }
-    rng -= spawn_rates.hopper.unwrap_or_default();
+    let from_left = spawn_rates.from_left.unwrap_or(0.5).clamp(0.0, 1.0) as f64;
+    let facing = if game_rng.spawning.gen_bool(from_left) {
+        Facing::Left
+    } else {
+        Facing::Right
+    };
 
-    // Climber
-    if rng <= spawn_rates.climber.unwrap_or_default() {
-        commands.spawn(ClimberSpawn);
+    let Some(kind) = spawn_rates.pick(roll) else {
         return;
-    }
-    rng -= spawn_rates.climber.unwrap_or_default();
-
-    // Lurker
-    if rng <= spawn_rates.lurker.unwrap_or_default() {
-        commands.spawn(LurkerSpawn);
+    };
+    let Some((variant, archetype)) = archetypes.pick_named(kind, &mut game_rng.spawning) else {
         return;
+    };
+
+    // Everything on the field or telegraphed counts, scripted waves included
+    let mut field_cost = 0.0;
+    let mut alive = HashMap::<EnemyKind, u32>::default();
+    for (enemy, kind) in enemies.iter() {
+        field_cost += enemy.cost;
+        *alive.entry(*kind).or_default() += 1;
     }
-    rng -= spawn_rates.lurker.unwrap_or_default();
-
-    // Giant
-    if rng <= spawn_rates.giant.unwrap_or_default() {
-        commands.spawn(GiantSpawn);
-        // return;
+    for telegraph in incoming.iter() {
+        field_cost += telegraph.cost;
+        *alive.entry(telegraph.kind).or_default() += 1;
     }
-    // rng -= spawn_chances.giant.unwrap_or_default();
+    let fits = |kind: EnemyKind, archetype: &EnemyArchetype| {
+        let capped = spawn_rates
+            .caps
+            .get(&kind)
+            .is_some_and(|cap| alive.get(&kind).copied().unwrap_or_default() >= *cap);
+        let over_budget = spawn_rates
+            .budget
+            .is_some_and(|budget| field_cost + archetype.cost > budget);
+        !capped && !over_budget
+    };
+
+    // Swap in the priciest enemy from this part of the run that still fits, or
+    // hold back until the field thins out
+    let picked = if fits(kind, archetype) {
+        Some((kind, variant))
+    } else {
+        archetypes
+            .iter()
+            .filter(|(other, _, other_archetype)| {
+                spawn_rates.weight(*other) > 0.0
+                    && other_archetype.cost <= archetype.cost
+                    && fits(*other, other_archetype)
+            })
+            .max_by(|a, b| {
+                a.2.cost
+                    .total_cmp(&b.2.cost)
+                    .then(a.0.cmp(&b.0))
+                    .then(a.1.cmp(b.1))
+            })
+            .map(|(kind, variant, _)| (kind, variant))
+    };
+    let Some((kind, variant)) = picked else {
+        return;
+    };
+
+    queue_spawn(
+        &mut commands,
+        kind,
+        SpawnParams {
+            facing: Some(facing),
+            variant: Some(variant.to_string()),
+            ..default()
+        },
+    );
 }
 
 fn hop(
@@ -318,28 +638,122 @@ fn hop_grounding(mut query: Query<(&mut Hop, &ShapeHits)>) {
     }
 }
 
+/// A projectile only hurts each enemy once, however long they touch
 fn enemy_hits(
-    proj_query: Query<(Entity, &PlayerProjectile)>,
+    mut proj_query: Query<&mut PlayerProjectile>,
     explosion_query: Query<(Entity, &Explosion)>,
-    mut query: Query<(&mut Enemy, &CollidingEntities)>,
+    mut query: Query<(Entity, &mut Enemy, &CollidingEntities, Option<&Armor>)>,
 ) {
-    for (mut enemy, colliding_entities) in query.iter_mut() {
+    for (entity, mut enemy, colliding_entities, armor) in query.iter_mut() {
+        let threshold = armor.map_or(0, |a| a.threshold);
+
         for coll_entity in colliding_entities.iter() {
-            for (proj_entity, projectile) in proj_query.iter() {
-                if coll_entity == &proj_entity {
-                    enemy.health -= projectile.size;
+            if let Ok(mut projectile) = proj_query.get_mut(*coll_entity) {
+                if !projectile.hits.contains(&entity) {
+                    projectile.hits.push(entity);
+                    if projectile.size >= threshold {
+                        enemy.health -= projectile.size;
+                        enemy.last_hit = DeathCause::Projectile {
+                            size: projectile.size,
+                        };
+                    }
                 }
             }
 
             for (ex_entity, explosion) in explosion_query.iter() {
-                if coll_entity == &ex_entity {
+                if coll_entity == &ex_entity && explosion.power >= threshold {
                     enemy.health -= explosion.power;
+                    enemy.last_hit = DeathCause::Explosion {
+                        power: explosion.power,
+                        chain: explosion.chain,
+                    };
                 }
             }
         }
     }
 }
 
+fn enemy_death(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &Enemy,
+            &EnemyKind,
+            &Transform,
+            Option<&DeathExplosion>,
+        ),
+        Changed<Enemy>,
+    >,
+    texture_assets: Res<TextureAssets>,
+    mut died_ev: EventWriter<EnemyDied>,
+) {
+    for (entity, enemy, kind, trans, death) in query.iter() {
+        if enemy.health > 0 {
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+
+        let overkill = enemy.health.abs();
+        died_ev.send(EnemyDied {
+            entity,
+            kind: *kind,
+            position: trans.translation.truncate(),
+            overkill,
+            points: enemy.points,
+            cause: enemy.last_hit,
+        });
+
+        let chain = match enemy.last_hit {
+            DeathCause::Explosion { chain, .. } => chain + 1,
+            _ => 1,
+        };
+        let death = death.copied().unwrap_or_default();
+        let power = ((overkill as f32 * death.scale).round() as i32).max(death.min_power);
+        spawn_explosion(
+            &mut commands,
+            &texture_assets,
+            trans.translation,
+            power,
+            chain,
+            death.duration,
+        );
+    }
+}
+
+pub(crate) fn spawn_explosion(
+    commands: &mut Commands,
+    texture_assets: &TextureAssets,
+    translation: Vec3,
+    power: i32,
+    chain: u32,
+    duration: f32,
+) {
+    commands.spawn(ExplosionBundle {
+        sprite_bundle: SpriteSheetBundle {
+            atlas: TextureAtlas {
+                layout: texture_assets.explosion_layout.clone(),
+                index: 0,
+            },
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(power as f32 * 2.0)),
+                ..Default::default()
+            },
+            texture: texture_assets.explosion.clone(),
+            transform: Transform::from_translation(translation),
+            ..Default::default()
+        },
+        collider: Collider::circle(power as f32),
+        explosion: Explosion {
+            power,
+            chain,
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        },
+        ..Default::default()
+    });
+}
+
 fn explosion_animate(
     time: Res<Time>,
     mut query: Query<(
@@ -0,0 +1,263 @@
+use crate::enemies::{ContactDamage, DeathExplosion, EnemyKind, Hop, HopBundle, SpawnParams};
+use crate::events::Threat;
+use crate::{DynamicActorBundle, PhysicsLayers};
+use bevy::{prelude::*, reflect::TypePath, utils::HashMap};
+use bevy_xpbd_2d::prelude::*;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Balance numbers for every enemy type, keyed by kind and then by variant name,
+/// e.g. `[hopper.default]`
+#[derive(serde::Deserialize, Clone, Default, TypePath, Asset, Resource)]
+pub struct EnemyArchetypes {
+    #[serde(flatten)]
+    kinds: HashMap<EnemyKind, BTreeMap<String, EnemyArchetype>>,
+}
+
+impl EnemyArchetypes {
+    /// Rolls one of the kind's variants by `weight`. `None` if the kind has no
+    /// variants, `validate` reports that when the archetypes load
+    pub(crate) fn pick(&self, kind: EnemyKind, rng: &mut impl Rng) -> Option<&EnemyArchetype> {
+        self.pick_named(kind, rng).map(|(_, archetype)| archetype)
+    }
+
+    /// Like `pick`, along with the variant's name
+    pub(crate) fn pick_named(
+        &self,
+        kind: EnemyKind,
+        rng: &mut impl Rng,
+    ) -> Option<(&str, &EnemyArchetype)> {
+        let variants = self.kinds.get(&kind).filter(|v| !v.is_empty())?;
+
+        let total: f32 = variants.values().map(|v| v.weight).sum();
+        let mut chance = if total > 0.0 {
+            rng.gen_range(0.0..total)
+        } else {
+            0.0
+        };
+
+        for (name, variant) in variants.iter() {
+            if chance < variant.weight {
+                return Some((name, variant));
+            }
+            chance -= variant.weight;
+        }
+
+        variants
+            .iter()
+            .next()
+            .map(|(name, variant)| (name.as_str(), variant))
+    }
+
+    /// The variant `params` asks for, or a rolled one if it doesn't name one
+    pub(crate) fn pick_for(
+        &self,
+        kind: EnemyKind,
+        params: Option<&SpawnParams>,
+        rng: &mut impl Rng,
+    ) -> Option<&EnemyArchetype> {
+        params
+            .and_then(|p| p.variant.as_deref())
+            .and_then(|variant| self.variant(kind, variant))
+            .or_else(|| self.pick(kind, rng))
+    }
+
+    pub(crate) fn variant(&self, kind: EnemyKind, name: &str) -> Option<&EnemyArchetype> {
+        self.kinds.get(&kind)?.get(name)
+    }
+
+    /// Kinds with nothing to spawn, and hopping variants without a `hop` table
+    pub(crate) fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for kind in EnemyKind::ALL {
+            if self.kinds.get(&kind).map_or(true, |v| v.is_empty()) {
+                errors.push(format!("no archetype for {kind:?}, it won't spawn"));
+            }
+        }
+        for (kind, name, archetype) in self.iter() {
+            if kind.hops() && archetype.hop.is_none() {
+                errors.push(format!(
+                    "[{}.{name}] has no hop table, it will sit still",
+                    format!("{kind:?}").to_lowercase()
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Every variant of every kind, with its name
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (EnemyKind, &str, &EnemyArchetype)> {
+        self.kinds.iter().flat_map(|(kind, variants)| {
+            variants
+                .iter()
+                .map(|(name, archetype)| (*kind, name.as_str(), archetype))
+        })
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub(crate) struct EnemyArchetype {
+    /// Chance of this variant relative to the kind's other variants
+    #[serde(default = "one")]
+    pub weight: f32,
+    pub size: [f32; 2],
+    pub health: i32,
+    #[serde(default)]
+    pub armor: i32,
+    /// Base score for killing it
+    #[serde(default)]
+    pub points: u32,
+    /// How much of the spawn table's `budget` it takes up while alive
+    #[serde(default = "one")]
+    pub cost: f32,
+    #[serde(default)]
+    pub mass: Option<f32>,
+    #[serde(default)]
+    pub friction: f32,
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    /// Spawn height range
+    pub height: [f32; 2],
+    /// How far past the edge of the screen it spawns
+    #[serde(default = "just_off_screen")]
+    pub edge_offset: [f32; 2],
+    /// Named spawn point, used instead of `height` and `edge_offset`
+    #[serde(default)]
+    pub spawn_point: Option<String>,
+    /// Seconds of warning before it appears
+    #[serde(default = "telegraph_time")]
+    pub telegraph: f32,
+    pub layers: Vec<PhysicsLayers>,
+    pub collides_with: Vec<PhysicsLayers>,
+    #[serde(default)]
+    pub death: DeathExplosion,
+    #[serde(default)]
+    pub contact: ContactDamage,
+    #[serde(default)]
+    pub hop: Option<HopArchetype>,
+
+    // Behaviour specific numbers, only read by the types that need them
+    #[serde(default)]
+    pub speed: f32,
+    #[serde(default)]
+    pub force: f32,
+    #[serde(default)]
+    pub impulse: [f32; 2],
+    #[serde(default)]
+    pub climb: [f32; 2],
+    #[serde(default)]
+    pub wait: [f32; 2],
+    #[serde(default)]
+    pub knockback: f32,
+    #[serde(default)]
+    pub dive_speed: f32,
+    #[serde(default)]
+    pub dive_distance: [f32; 2],
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub(crate) struct HopArchetype {
+    pub timer: f32,
+    pub reset_timer: f32,
+    pub power_x: [f32; 2],
+    pub power_y: [f32; 2],
+    pub cast_distance: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn telegraph_time() -> f32 {
+    0.75
+}
+
+fn just_off_screen() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// Random value in `[min, max)`, or `min` if the range is empty
+pub(crate) fn roll(rng: &mut impl Rng, range: [f32; 2]) -> f32 {
+    if range[0] < range[1] {
+        rng.gen_range(range[0]..range[1])
+    } else {
+        range[0]
+    }
+}
+
+fn layer_mask(layers: &[PhysicsLayers]) -> LayerMask {
+    let mut mask = LayerMask::NONE;
+    for layer in layers {
+        mask.add(*layer);
+    }
+    mask
+}
+
+impl EnemyArchetype {
+    pub fn size(&self) -> Vec2 {
+        Vec2::from(self.size)
+    }
+
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color[0], self.color[1], self.color[2])
+    }
+
+    pub fn dynamic_actor_bundle(&self, velocity: Vec2) -> DynamicActorBundle {
+        let size = self.size();
+        DynamicActorBundle {
+            collider: Collider::rectangle(size.x, size.y),
+            collision_layers: CollisionLayers::new(
+                layer_mask(&self.layers),
+                layer_mask(&self.collides_with),
+            ),
+            friction: Friction::new(self.friction),
+            restitution: Restitution::new(self.restitution),
+            mass: self.mass.map(Mass).unwrap_or_default(),
+            velocity: LinearVelocity(velocity),
+            ..Default::default()
+        }
+    }
+
+    /// A copy made tougher and faster by the run's threat
+    pub fn escalated(&self, threat: &Threat) -> EnemyArchetype {
+        let mut archetype = self.clone();
+        archetype.health = (self.health as f32 * threat.health).round() as i32;
+        archetype.speed *= threat.speed;
+        archetype.force *= threat.speed;
+        archetype.impulse[0] *= threat.speed;
+        archetype.dive_speed *= threat.speed;
+        if let Some(hop) = archetype.hop.as_mut() {
+            hop.power_x = hop.power_x.map(|power| power * threat.speed);
+        }
+        archetype
+    }
+
+    /// Without a `hop` table the enemy never leaves the ground
+    pub fn hop_bundle(&self, facing_mul: f32, rng: &mut impl Rng) -> HopBundle {
+        let hop = self.hop.clone().unwrap_or_default();
+        let size = self.size();
+
+        HopBundle {
+            hop: Hop {
+                grounded: false,
+                hop_timer: Timer::from_seconds(hop.timer, TimerMode::Once),
+                hop_reset_timer: Timer::from_seconds(hop.reset_timer, TimerMode::Once),
+                power: Vec2::new(roll(rng, hop.power_x) * facing_mul, roll(rng, hop.power_y)),
+            },
+            caster: ShapeCaster::new(
+                Collider::rectangle(size.x, size.y),
+                Vec2::ZERO,
+                0.0,
+                Direction2d::NEG_Y,
+            )
+            .with_max_time_of_impact(hop.cast_distance)
+            .with_query_filter(SpatialQueryFilter::from_mask(PhysicsLayers::Ground)),
+        }
+    }
+}
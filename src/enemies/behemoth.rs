@@ -0,0 +1,97 @@
+use crate::enemies::archetype::EnemyArchetypes;
+use crate::enemies::spawn_point::SpawnPoints;
+use crate::enemies::telegraph::Telegraph;
+use crate::enemies::{
+    roll_facing, Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+};
+use crate::events::Threat;
+use crate::rng::GameRng;
+use crate::world::ArenaEntity;
+use crate::{running, DynamicActorBundle};
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::HopBundle;
+
+#[derive(Component, Default)]
+pub(crate) struct BehemothSpawn;
+
+#[derive(Component, Default)]
+pub struct Behemoth;
+
+#[derive(Bundle, Default)]
+struct BehemothBundle {
+    sprite_bundle: SpriteBundle,
+    dynamic_actor_bundle: DynamicActorBundle,
+    enemy: Enemy,
+    death: DeathExplosion,
+    contact: ContactDamage,
+    behemoth: Behemoth,
+    armor: Armor,
+    hop: HopBundle,
+    external_force: ExternalForce,
+}
+
+pub struct BehemothPlugin;
+
+impl Plugin for BehemothPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn.run_if(running));
+    }
+}
+
+fn spawn(
+    query: Query<(Entity, Option<&SpawnParams>), (With<BehemothSpawn>, Without<Telegraph>)>,
+    mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    for (entity, params) in query.iter() {
+        commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
+
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Behemoth, params, rng) else {
+            continue;
+        };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
+        let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
+
+        commands.spawn((
+            BehemothBundle {
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
+                },
+                dynamic_actor_bundle: archetype.dynamic_actor_bundle(Vec2::ZERO),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
+                },
+                death: archetype.death,
+                contact: archetype.contact,
+                armor: Armor {
+                    threshold: archetype.armor,
+                },
+                hop: archetype.hop_bundle(facing_mul, rng),
+                external_force: ExternalForce::new(Vec2::new(archetype.force * facing_mul, 0.0))
+                    .with_persistence(true),
+                ..Default::default()
+            },
+            EnemyKind::Behemoth,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
+    }
+}
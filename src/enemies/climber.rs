@@ -1,25 +1,35 @@
 use crate::{
-    enemies::{Enemy, Explosion, ExplosionBundle, Facing},
-    loading::TextureAssets,
-    DynamicActorBundle, GameState, PhysicsLayers,
+    enemies::{
+        archetype::EnemyArchetypes, roll_facing, spawn_point::SpawnPoints, telegraph::Telegraph,
+        Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+    },
+    events::Threat,
+    rng::GameRng,
+    running,
+    world::ArenaEntity,
+    DynamicActorBundle, PhysicsLayers,
 };
+#[cfg(debug_assertions)]
+use crate::{headless::Headless, GameState};
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
-use rand::Rng;
-
-const CLIMBER_SHAPE: Vec2 = Vec2::new(1.0, 2.0);
 
 #[derive(Component, Default)]
 pub(crate) struct ClimberSpawn;
 
 #[derive(Component)]
-pub(crate) struct Climber;
+pub(crate) struct Climber {
+    climb: Vec2,
+}
 
 #[derive(Bundle)]
 struct ClimberBundle {
     sprite_bundle: SpriteBundle,
     dynamic_actor_bundle: DynamicActorBundle,
     enemy: Enemy,
+    armor: Armor,
+    death: DeathExplosion,
+    contact: ContactDamage,
     climber: Climber,
 }
 
@@ -27,75 +37,93 @@ pub struct ClimberPlugin;
 
 impl Plugin for ClimberPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (climb, spawn, health).run_if(in_state(GameState::Playing)),
-        );
+        app.add_systems(Update, (climb, spawn).run_if(running));
 
         #[cfg(debug_assertions)]
         {
-            app.add_systems(Update, debug_colliders.run_if(in_state(GameState::Playing)));
+            app.add_systems(
+                Update,
+                debug_colliders
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<Headless>)),
+            );
         }
     }
 }
 
 #[cfg(debug_assertions)]
-fn debug_colliders(query: Query<&Position, With<Climber>>, mut gizmos: Gizmos) {
-    for p in query.iter() {
-        gizmos.rect_2d(p.0, 0.0, CLIMBER_SHAPE, Color::PINK);
+fn debug_colliders(query: Query<(&Position, &Sprite), With<Climber>>, mut gizmos: Gizmos) {
+    for (p, sprite) in query.iter() {
+        gizmos.rect_2d(
+            p.0,
+            0.0,
+            sprite.custom_size.unwrap_or_default(),
+            Color::PINK,
+        );
     }
 }
 
-fn spawn(query: Query<(Entity, &ClimberSpawn)>, mut commands: Commands) {
-    for (entity, _spawn) in query.iter() {
+fn spawn(
+    query: Query<(Entity, Option<&SpawnParams>), (With<ClimberSpawn>, Without<Telegraph>)>,
+    mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    for (entity, params) in query.iter() {
         commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
 
-        let facing = if rand::thread_rng().gen_bool(0.5) {
-            Facing::Left
-        } else {
-            Facing::Right
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Climber, params, rng) else {
+            continue;
         };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
         let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
 
-        commands.spawn(ClimberBundle {
-            sprite_bundle: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::MIDNIGHT_BLUE,
-                    custom_size: Some(CLIMBER_SHAPE),
-                    ..default()
+        commands.spawn((
+            ClimberBundle {
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
+                },
+                dynamic_actor_bundle: archetype
+                    .dynamic_actor_bundle(Vec2::new(archetype.speed * facing_mul, 0.0)),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
+                },
+                armor: Armor {
+                    threshold: archetype.armor,
+                },
+                death: archetype.death,
+                contact: archetype.contact,
+                climber: Climber {
+                    climb: Vec2::from(archetype.climb),
                 },
-                transform: Transform::from_translation(Vec3::new(16.0 * -facing_mul, 1.0, 0.0)),
-                ..Default::default()
-            },
-            dynamic_actor_bundle: DynamicActorBundle {
-                rigidbody: RigidBody::Dynamic,
-                collider: Collider::rectangle(CLIMBER_SHAPE.x, CLIMBER_SHAPE.y),
-                collision_layers: CollisionLayers::new(
-                    [PhysicsLayers::Enemy, PhysicsLayers::Climber],
-                    [
-                        PhysicsLayers::Ground,
-                        PhysicsLayers::CliffEdge,
-                        PhysicsLayers::Player,
-                        PhysicsLayers::PlayerProj,
-                        PhysicsLayers::Explosion,
-                    ],
-                ),
-                friction: Friction::ZERO,
-                restitution: Restitution::PERFECTLY_INELASTIC,
-                velocity: LinearVelocity(Vec2::new(facing_mul * 2.0, 0.0)),
-                ..Default::default()
             },
-            enemy: Enemy { health: 1, facing },
-            climber: Climber,
-        });
+            EnemyKind::Climber,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
     }
 }
 
 fn climb(
-    mut query: Query<(&mut LinearVelocity, &CollidingEntities, &Enemy), With<Climber>>,
+    mut query: Query<(&mut LinearVelocity, &CollidingEntities, &Enemy, &Climber)>,
     sensor_query: Query<(Entity, &CollisionLayers), With<Sensor>>,
 ) {
-    for (mut velocity, colliding_entities, enemy) in query.iter_mut() {
+    for (mut velocity, colliding_entities, enemy, climber) in query.iter_mut() {
         for e in colliding_entities.iter() {
             for (entity, collision_layers) in sensor_query.iter() {
                 if e == &entity
@@ -104,49 +132,11 @@ fn climb(
                         .has_all(PhysicsLayers::CliffEdge)
                 {
                     let mul: f32 = enemy.facing.into();
-                    velocity.x = 1.0 * mul;
-                    velocity.y = 9.0;
+                    velocity.x = climber.climb.x * mul;
+                    velocity.y = climber.climb.y;
                     return;
                 }
             }
         }
     }
 }
-
-fn health(
-    mut commands: Commands,
-    query: Query<(Entity, &Enemy, &Transform), (With<Climber>, Changed<Enemy>)>,
-    texture_assets: Res<TextureAssets>,
-) {
-    for (entity, enemy, trans) in query.iter() {
-        if enemy.health <= 0 {
-            let _ = &commands.entity(entity).despawn();
-
-            // Spawn Explosion
-            commands.spawn(ExplosionBundle {
-                sprite_bundle: SpriteSheetBundle {
-                    atlas: TextureAtlas {
-                        layout: texture_assets.explosion_layout.clone(),
-                        index: 0,
-                    },
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(
-                            enemy.health.abs() as f32 * 2.0,
-                            enemy.health.abs() as f32 * 2.0,
-                        )),
-                        ..Default::default()
-                    },
-                    texture: texture_assets.explosion.clone(),
-                    transform: Transform::from_translation(trans.translation),
-                    ..Default::default()
-                },
-                collider: Collider::circle(enemy.health.abs() as f32),
-                explosion: Explosion {
-                    power: enemy.health.abs(),
-                    timer: Timer::from_seconds(0.5, TimerMode::Once),
-                },
-                ..Default::default()
-            });
-        }
-    }
-}
@@ -0,0 +1,140 @@
+use crate::enemies::archetype::{roll, EnemyArchetypes};
+use crate::enemies::spawn_point::SpawnPoints;
+use crate::enemies::telegraph::Telegraph;
+use crate::enemies::{
+    roll_facing, Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+};
+use crate::events::Threat;
+use crate::player::PLAYER_CENTER;
+use crate::rng::GameRng;
+use crate::world::ArenaEntity;
+use crate::world::Ground;
+use crate::{running, DynamicActorBundle};
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use rand::Rng;
+
+#[derive(Component, Default)]
+pub(crate) struct DiverSpawn;
+
+#[derive(Component)]
+pub(crate) struct Diver {
+    target: Vec2,
+    dive_distance: f32,
+    glide_speed: f32,
+    dive_speed: f32,
+    diving: bool,
+}
+
+#[derive(Bundle)]
+struct DiverBundle {
+    sprite_bundle: SpriteBundle,
+    dynamic_actor_bundle: DynamicActorBundle,
+    gravity_scale: GravityScale,
+    enemy: Enemy,
+    armor: Armor,
+    death: DeathExplosion,
+    contact: ContactDamage,
+    diver: Diver,
+}
+
+pub struct DiverPlugin;
+
+impl Plugin for DiverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn, glide, crash).run_if(running));
+    }
+}
+
+fn spawn(
+    query: Query<(Entity, Option<&SpawnParams>), (With<DiverSpawn>, Without<Telegraph>)>,
+    mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    for (entity, params) in query.iter() {
+        commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
+
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Diver, params, rng) else {
+            continue;
+        };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
+        let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
+
+        let target = PLAYER_CENTER + Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-0.5..0.5));
+
+        commands.spawn((
+            DiverBundle {
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
+                },
+                dynamic_actor_bundle: archetype
+                    .dynamic_actor_bundle(Vec2::new(archetype.speed * facing_mul, 0.0)),
+                gravity_scale: GravityScale(0.0),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
+                },
+                armor: Armor {
+                    threshold: archetype.armor,
+                },
+                death: archetype.death,
+                contact: archetype.contact,
+                diver: Diver {
+                    target,
+                    dive_distance: roll(rng, archetype.dive_distance),
+                    glide_speed: archetype.speed,
+                    dive_speed: archetype.dive_speed,
+                    diving: false,
+                },
+            },
+            EnemyKind::Diver,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
+    }
+}
+
+fn glide(mut query: Query<(&mut LinearVelocity, &mut Diver, &Transform, &Enemy)>) {
+    for (mut velocity, mut diver, trans, enemy) in query.iter_mut() {
+        if diver.diving {
+            continue;
+        }
+
+        let pos = trans.translation.truncate();
+        if (diver.target.x - pos.x).abs() <= diver.dive_distance {
+            // Commit to the dive, the diver won't correct its course after this
+            diver.diving = true;
+            velocity.0 = (diver.target - pos).normalize_or_zero() * diver.dive_speed;
+        } else {
+            let mul: f32 = enemy.facing.into();
+            velocity.x = diver.glide_speed * mul;
+            velocity.y = 0.0;
+        }
+    }
+}
+
+fn crash(
+    mut query: Query<(&mut Enemy, &Diver, &CollidingEntities)>,
+    ground_query: Query<(), With<Ground>>,
+) {
+    for (mut enemy, diver, colliding_entities) in query.iter_mut() {
+        if diver.diving && colliding_entities.iter().any(|e| ground_query.contains(*e)) {
+            enemy.health = 0;
+        }
+    }
+}
@@ -1,10 +1,15 @@
+use crate::difficulty::DifficultySettings;
+use crate::enemies::{ContactDamage, ContactResponse};
 use crate::loading::TextureAssets;
 use crate::player::PLAYER_CENTER;
-use crate::{DynamicActorBundle, GameState, PhysicsLayers};
+use crate::world::ArenaEntity;
+use crate::{running, DynamicActorBundle, PhysicsLayers};
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 
 const PROJECTILE_SHAPE: Vec2 = Vec2::new(0.3, 0.3);
+/// Damage to the player before the difficulty's multiplier
+const PROJECTILE_DAMAGE: f32 = 1.0;
 
 #[derive(Component, Default)]
 pub(crate) struct ProjectileSpawn {
@@ -20,6 +25,7 @@ struct ProjectileChildBundle {
     dynamic_actor_bundle: DynamicActorBundle,
     sensor: Sensor,
     projectile: EnemyProjectile,
+    contact: ContactDamage,
 }
 
 pub struct ProjectilePlugin;
@@ -28,7 +34,7 @@ impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            (spawn, projectile_destruction, animate).run_if(in_state(GameState::Playing)),
+            (spawn, projectile_destruction, animate).run_if(running),
         );
     }
 }
@@ -37,7 +43,10 @@ fn spawn(
     query: Query<(Entity, &ProjectileSpawn)>,
     mut commands: Commands,
     texture_assets: Res<TextureAssets>,
+    difficulty: Res<DifficultySettings>,
 ) {
+    let damage = (PROJECTILE_DAMAGE * difficulty.preset.projectile_damage).round() as i32;
+
     for (entity, spawn) in query.iter() {
         commands.entity(entity).despawn();
 
@@ -50,6 +59,7 @@ fn spawn(
                 RigidBody::Kinematic,
                 LockedAxes::ROTATION_LOCKED,
                 LinearVelocity((PLAYER_CENTER - spawn.pos).normalize()),
+                ArenaEntity,
             ))
             .with_children(|parent| {
                 parent.spawn(ProjectileChildBundle {
@@ -76,6 +86,11 @@ fn spawn(
                         ),
                         ..Default::default()
                     },
+                    contact: ContactDamage {
+                        damage,
+                        response: ContactResponse::Nothing,
+                        knockback: 0.0,
+                    },
                     ..Default::default()
                 });
             });
@@ -1,26 +1,35 @@
-use crate::enemies::{Enemy, Explosion, ExplosionBundle, Facing, Hop};
-use crate::loading::TextureAssets;
+use crate::enemies::archetype::EnemyArchetypes;
+use crate::enemies::spawn_point::SpawnPoints;
+use crate::enemies::telegraph::Telegraph;
+use crate::enemies::{
+    roll_facing, Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+};
+use crate::events::Threat;
 use crate::player::PlayerProjectile;
-use crate::{DynamicActorBundle, GameState, PhysicsLayers};
+use crate::rng::GameRng;
+use crate::world::ArenaEntity;
+use crate::{running, DynamicActorBundle};
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
-use rand::Rng;
 
 use super::HopBundle;
 
-const COLLIDER_SHAPE: Vec2 = Vec2::new(3.0, 6.0);
-
 #[derive(Component, Default)]
 pub(crate) struct GiantSpawn;
 
 #[derive(Component, Default)]
-pub struct Giant;
+pub struct Giant {
+    knockback: f32,
+}
 
 #[derive(Bundle, Default)]
 struct GiantBundle {
     sprite_bundle: SpriteBundle,
     dynamic_actor_bundle: DynamicActorBundle,
     enemy: Enemy,
+    armor: Armor,
+    death: DeathExplosion,
+    contact: ContactDamage,
     giant: Giant,
     hop: HopBundle,
     external_force: ExternalForce,
@@ -30,126 +39,85 @@ pub struct GiantPlugin;
 
 impl Plugin for GiantPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (spawn, hit, health).run_if(in_state(GameState::Playing)),
-        );
+        app.add_systems(Update, (spawn, hit).run_if(running));
     }
 }
 
-fn spawn(query: Query<(Entity, &GiantSpawn)>, mut commands: Commands) {
-    for (entity, _spawn) in query.iter() {
+fn spawn(
+    query: Query<(Entity, Option<&SpawnParams>), (With<GiantSpawn>, Without<Telegraph>)>,
+    mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    for (entity, params) in query.iter() {
         commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
 
-        let facing = if rand::thread_rng().gen_bool(0.5) {
-            Facing::Left
-        } else {
-            Facing::Right
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Giant, params, rng) else {
+            continue;
         };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
         let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
 
-        let power = Vec2::new(0.0, rand::thread_rng().gen_range(1200.0..1201.0));
-
-        commands.spawn(GiantBundle {
-            sprite_bundle: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::BLUE,
-                    custom_size: Some(COLLIDER_SHAPE),
-                    ..default()
+        commands.spawn((
+            GiantBundle {
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
                 },
-                transform: Transform::from_translation(Vec3::new(16.0 * -facing_mul, 6.0, 0.0)),
-                ..Default::default()
-            },
-            dynamic_actor_bundle: DynamicActorBundle {
-                collider: Collider::rectangle(COLLIDER_SHAPE.x, COLLIDER_SHAPE.y),
-                collision_layers: CollisionLayers::new(
-                    [PhysicsLayers::Enemy, PhysicsLayers::Giant],
-                    [
-                        PhysicsLayers::Ground,
-                        PhysicsLayers::Player,
-                        PhysicsLayers::PlayerProj,
-                    ],
-                ),
-                mass: Mass(100.0),
-                friction: Friction::new(2.0),
-                restitution: Restitution::new(0.2),
-                ..Default::default()
-            },
-            enemy: Enemy { health: 20, facing },
-            hop: HopBundle {
-                hop: Hop {
-                    grounded: false,
-                    hop_timer: Timer::from_seconds(0.5, TimerMode::Once),
-                    hop_reset_timer: Timer::from_seconds(1.0, TimerMode::Once),
-                    power,
+                dynamic_actor_bundle: archetype.dynamic_actor_bundle(Vec2::ZERO),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
+                },
+                armor: Armor {
+                    threshold: archetype.armor,
+                },
+                death: archetype.death,
+                contact: archetype.contact,
+                giant: Giant {
+                    knockback: archetype.knockback,
                 },
-                caster: ShapeCaster::new(
-                    Collider::rectangle(COLLIDER_SHAPE.x, COLLIDER_SHAPE.y),
-                    Vec2::ZERO,
-                    0.0,
-                    Direction2d::NEG_Y,
-                )
-                .with_max_time_of_impact(0.1)
-                .with_query_filter(SpatialQueryFilter::from_mask(PhysicsLayers::Ground)),
+                hop: archetype.hop_bundle(facing_mul, rng),
+                external_force: ExternalForce::new(Vec2::new(archetype.force * facing_mul, 0.0))
+                    .with_persistence(true),
+                ..Default::default()
             },
-            external_force: ExternalForce::new(Vec2::new(25.0 * facing_mul, 0.0))
-                .with_persistence(true),
-            ..Default::default()
-        });
+            EnemyKind::Giant,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
     }
 }
 
 fn hit(
     proj_query: Query<(Entity, &PlayerProjectile)>,
-    mut query: Query<(&mut ExternalImpulse, &Enemy, &CollidingEntities), With<Giant>>,
+    mut query: Query<(&mut ExternalImpulse, &Enemy, &Giant, &CollidingEntities)>,
 ) {
-    for (mut force, enemy, colliding_entities) in query.iter_mut() {
+    for (mut force, enemy, giant, colliding_entities) in query.iter_mut() {
         for coll_entity in colliding_entities.iter() {
             for (proj_entity, projectile) in proj_query.iter() {
                 if coll_entity == &proj_entity {
                     force.set_impulse(
-                        Vec2::X * -f32::from(enemy.facing) * (projectile.size as f32) * 50.0,
+                        Vec2::X
+                            * -f32::from(enemy.facing)
+                            * (projectile.size as f32)
+                            * giant.knockback,
                     );
                 }
             }
         }
     }
 }
-
-fn health(
-    mut commands: Commands,
-    query: Query<(Entity, &Enemy, &Transform), (With<Giant>, Changed<Enemy>)>,
-    texture_assets: Res<TextureAssets>,
-) {
-    for (entity, enemy, trans) in query.iter() {
-        if enemy.health <= 0 {
-            let _ = &commands.entity(entity).despawn();
-
-            // Spawn Explosion
-            commands.spawn(ExplosionBundle {
-                sprite_bundle: SpriteSheetBundle {
-                    atlas: TextureAtlas {
-                        layout: texture_assets.explosion_layout.clone(),
-                        index: 0,
-                    },
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(
-                            enemy.health.abs() as f32 * 2.0,
-                            enemy.health.abs() as f32 * 2.0,
-                        )),
-                        ..Default::default()
-                    },
-                    texture: texture_assets.explosion.clone(),
-                    transform: Transform::from_translation(trans.translation),
-                    ..Default::default()
-                },
-                collider: Collider::circle(enemy.health.abs() as f32),
-                explosion: Explosion {
-                    power: enemy.health.abs(),
-                    timer: Timer::from_seconds(0.5, TimerMode::Once),
-                },
-                ..Default::default()
-            });
-        }
-    }
-}
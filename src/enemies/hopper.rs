@@ -1,15 +1,21 @@
+use crate::enemies::archetype::EnemyArchetypes;
 use crate::enemies::enemy_projectile::ProjectileSpawn;
-use crate::enemies::{Enemy, Explosion, ExplosionBundle, Facing, Hop};
+use crate::enemies::spawn_point::SpawnPoints;
+use crate::enemies::telegraph::Telegraph;
+use crate::enemies::{
+    roll_facing, Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+};
+use crate::events::Threat;
 use crate::loading::TextureAssets;
-use crate::{DynamicActorBundle, GameState, PhysicsLayers};
+use crate::rng::GameRng;
+use crate::world::ArenaEntity;
+use crate::{running, DynamicActorBundle};
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 use rand::Rng;
 
 use super::HopBundle;
 
-const COLLIDER_SHAPE: Vec2 = Vec2::new(2.0, 2.0);
-
 #[derive(Component, Default)]
 pub(crate) struct HopperSpawn;
 
@@ -21,6 +27,9 @@ struct HopperBundle {
     sprite_bundle: SpriteSheetBundle,
     dynamic_actor_bundle: DynamicActorBundle,
     enemy: Enemy,
+    armor: Armor,
+    death: DeathExplosion,
+    contact: ContactDamage,
     hopper: Hopper,
     hop: HopBundle,
     external_force: ExternalForce,
@@ -30,92 +39,79 @@ pub struct HopperPlugin;
 
 impl Plugin for HopperPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (spawn, shoot, health, animate).run_if(in_state(GameState::Playing)),
-        );
+        app.add_systems(Update, (spawn, shoot, animate).run_if(running));
     }
 }
 
 fn spawn(
-    query: Query<(Entity, &HopperSpawn)>,
+    query: Query<(Entity, Option<&SpawnParams>), (With<HopperSpawn>, Without<Telegraph>)>,
     mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
     texture_assets: Res<TextureAssets>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    for (entity, _spawn) in query.iter() {
+    for (entity, params) in query.iter() {
         commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
 
-        let facing = if rand::thread_rng().gen_bool(0.5) {
-            Facing::Left
-        } else {
-            Facing::Right
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Hopper, params, rng) else {
+            continue;
         };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
         let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
 
-        let power = Vec2::new(
-            rand::thread_rng().gen_range(3.0..3.1) * facing_mul,
-            rand::thread_rng().gen_range(50.0..55.0),
-        );
-
-        let height = rand::thread_rng().gen_range(5f32..10f32);
-
-        commands.spawn(HopperBundle {
-            sprite_bundle: SpriteSheetBundle {
-                atlas: TextureAtlas {
-                    layout: texture_assets.hopper_layout.clone(),
-                    index: 0,
+        commands.spawn((
+            HopperBundle {
+                sprite_bundle: SpriteSheetBundle {
+                    atlas: TextureAtlas {
+                        layout: texture_assets.hopper_layout.clone(),
+                        index: 0,
+                    },
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        flip_x: facing.into(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    texture: texture_assets.hopper.clone(),
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
                 },
-                sprite: Sprite {
-                    flip_x: facing.into(),
-                    custom_size: Some(COLLIDER_SHAPE),
-                    ..default()
+                dynamic_actor_bundle: archetype
+                    .dynamic_actor_bundle(Vec2::new(archetype.speed * facing_mul, 0.0)),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
                 },
-                texture: texture_assets.hopper.clone(),
-                transform: Transform::from_translation(Vec3::new(16.0 * -facing_mul, height, 0.0)),
-                ..Default::default()
-            },
-            dynamic_actor_bundle: DynamicActorBundle {
-                collider: Collider::rectangle(COLLIDER_SHAPE.x, COLLIDER_SHAPE.y),
-                collision_layers: CollisionLayers::new(
-                    [PhysicsLayers::Enemy, PhysicsLayers::Hopper],
-                    [
-                        PhysicsLayers::Ground,
-                        PhysicsLayers::Hopper,
-                        PhysicsLayers::Player,
-                        PhysicsLayers::PlayerProj,
-                        PhysicsLayers::Explosion,
-                    ],
-                ),
-                friction: Friction::new(2.0),
-                restitution: Restitution::new(1.0),
-                velocity: Vec2::new(2.0 * facing_mul, 0.0).into(),
-                ..Default::default()
-            },
-            enemy: Enemy { health: 1, facing },
-            hop: HopBundle {
-                hop: Hop {
-                    grounded: false,
-                    hop_timer: Timer::from_seconds(0.16, TimerMode::Once),
-                    hop_reset_timer: Timer::from_seconds(0.5, TimerMode::Once),
-                    power,
+                armor: Armor {
+                    threshold: archetype.armor,
                 },
-                caster: ShapeCaster::new(
-                    Collider::rectangle(COLLIDER_SHAPE.x, COLLIDER_SHAPE.y),
-                    Vec2::ZERO,
-                    0.0,
-                    Direction2d::NEG_Y,
-                )
-                .with_max_time_of_impact(0.05)
-                .with_query_filter(SpatialQueryFilter::from_mask(PhysicsLayers::Ground)),
+                death: archetype.death,
+                contact: archetype.contact,
+                hop: archetype.hop_bundle(facing_mul, rng),
+                ..Default::default()
             },
-            ..Default::default()
-        });
+            EnemyKind::Hopper,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
     }
 }
 
-fn shoot(mut commands: Commands, query: Query<&Transform, With<Hopper>>) {
+fn shoot(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    query: Query<&Transform, With<Hopper>>,
+) {
     for t in query.iter() {
-        if rand::thread_rng().gen_range(0.0..1.0) > 0.999 && t.translation.x.abs() < 15.0 {
+        if game_rng.ai.gen_range(0.0..1.0) > 0.999 && t.translation.x.abs() < 15.0 {
             commands.spawn(ProjectileSpawn {
                 pos: t.translation.truncate(),
             });
@@ -134,41 +130,3 @@ fn animate(mut query: Query<(&mut TextureAtlas, &LinearVelocity)>) {
         }
     }
 }
-
-fn health(
-    mut commands: Commands,
-    query: Query<(Entity, &Enemy, &Transform), (With<Hopper>, Changed<Enemy>)>,
-    texture_assets: Res<TextureAssets>,
-) {
-    for (entity, enemy, trans) in query.iter() {
-        if enemy.health <= 0 {
-            let _ = &commands.entity(entity).despawn();
-
-            // Spawn Explosion
-            commands.spawn(ExplosionBundle {
-                sprite_bundle: SpriteSheetBundle {
-                    atlas: TextureAtlas {
-                        layout: texture_assets.explosion_layout.clone(),
-                        index: 0,
-                    },
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(
-                            enemy.health.abs() as f32 * 2.0,
-                            enemy.health.abs() as f32 * 2.0,
-                        )),
-                        ..Default::default()
-                    },
-                    texture: texture_assets.explosion.clone(),
-                    transform: Transform::from_translation(trans.translation),
-                    ..Default::default()
-                },
-                collider: Collider::circle(enemy.health.abs() as f32),
-                explosion: Explosion {
-                    power: enemy.health.abs(),
-                    timer: Timer::from_seconds(0.5, TimerMode::Once),
-                },
-                ..Default::default()
-            });
-        }
-    }
-}
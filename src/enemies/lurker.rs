@@ -1,13 +1,19 @@
 use crate::{
-    enemies::{Enemy, Explosion, ExplosionBundle, Facing},
-    loading::TextureAssets,
-    DynamicActorBundle, GameState, PhysicsLayers,
+    enemies::{
+        archetype::{roll, EnemyArchetypes},
+        roll_facing,
+        spawn_point::SpawnPoints,
+        telegraph::Telegraph,
+        Armor, ContactDamage, DeathExplosion, Enemy, EnemyKind, SpawnParams,
+    },
+    events::Threat,
+    rng::GameRng,
+    running,
+    world::ArenaEntity,
+    DynamicActorBundle,
 };
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
-use rand::Rng;
-
-const LURKER_SHAPE: Vec2 = Vec2::new(1.0, 2.0);
 
 #[derive(Component, Default)]
 pub(crate) struct LurkerSpawn;
@@ -16,6 +22,7 @@ pub(crate) struct LurkerSpawn;
 pub(crate) struct Lurker {
     step: u8,
     timer: Timer,
+    lunge: Vec2,
 }
 
 #[derive(Bundle)]
@@ -25,6 +32,9 @@ struct LurkerBundle {
     // active_collision_types: ActiveCollisionTypes,
     external_impulse: ExternalImpulse,
     enemy: Enemy,
+    armor: Armor,
+    death: DeathExplosion,
+    contact: ContactDamage,
     lurker: Lurker,
 }
 
@@ -32,60 +42,68 @@ pub struct LurkerPlugin;
 
 impl Plugin for LurkerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (lurk, spawn, health).run_if(in_state(GameState::Playing)),
-        );
+        app.add_systems(Update, (lurk, spawn).run_if(running));
     }
 }
 
-fn spawn(query: Query<(Entity, &LurkerSpawn)>, mut commands: Commands) {
-    for (entity, _spawn) in query.iter() {
+fn spawn(
+    query: Query<(Entity, Option<&SpawnParams>), (With<LurkerSpawn>, Without<Telegraph>)>,
+    mut commands: Commands,
+    archetypes: Res<EnemyArchetypes>,
+    threat: Res<Threat>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    for (entity, params) in query.iter() {
         commands.entity(entity).despawn();
+        let rng = &mut game_rng.spawning;
 
-        let facing = if rand::thread_rng().gen_bool(0.5) {
-            Facing::Left
-        } else {
-            Facing::Right
+        let Some(archetype) = archetypes.pick_for(EnemyKind::Lurker, params, rng) else {
+            continue;
         };
+        let archetype = archetype.escalated(&threat);
+        let facing = roll_facing(params, rng);
         let facing_mul: f32 = facing.into();
+        let position = spawn_points.position(&archetype, params, facing_mul, rng);
 
-        commands.spawn(LurkerBundle {
-            sprite_bundle: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::PURPLE,
-                    custom_size: Some(LURKER_SHAPE),
-                    ..default()
+        commands.spawn((
+            LurkerBundle {
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color: archetype.color(),
+                        custom_size: Some(archetype.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    ..Default::default()
+                },
+                dynamic_actor_bundle: archetype.dynamic_actor_bundle(Vec2::ZERO),
+                external_impulse: ExternalImpulse::new(Vec2::new(
+                    archetype.impulse[0] * facing_mul,
+                    0.0,
+                )),
+                enemy: Enemy {
+                    health: archetype.health,
+                    facing,
+                    points: archetype.points,
+                    cost: archetype.cost,
+                    ..Default::default()
+                },
+                armor: Armor {
+                    threshold: archetype.armor,
+                },
+                death: archetype.death,
+                contact: archetype.contact,
+                lurker: Lurker {
+                    step: 0,
+                    timer: Timer::from_seconds(roll(rng, archetype.wait), TimerMode::Once),
+                    lunge: Vec2::from(archetype.impulse),
                 },
-                transform: Transform::from_translation(Vec3::new(16.0 * -facing_mul, 1.0, 0.0)),
-                ..Default::default()
-            },
-            dynamic_actor_bundle: DynamicActorBundle {
-                rigidbody: RigidBody::Dynamic,
-                collider: Collider::rectangle(LURKER_SHAPE.x, LURKER_SHAPE.y),
-                collision_layers: CollisionLayers::new(
-                    [PhysicsLayers::Enemy, PhysicsLayers::Lurker],
-                    [
-                        PhysicsLayers::Ground,
-                        PhysicsLayers::Player,
-                        PhysicsLayers::PlayerProj,
-                        PhysicsLayers::Explosion,
-                    ],
-                ),
-                friction: Friction::new(6.0),
-                restitution: Restitution::new(0.0),
-                ..Default::default()
-            },
-            external_impulse: ExternalImpulse::new(Vec2::new(20.0 * facing_mul, 0.0)),
-            enemy: Enemy { health: 1, facing },
-            lurker: Lurker {
-                step: 0,
-                timer: Timer::from_seconds(
-                    rand::thread_rng().gen_range(3f32..5f32),
-                    TimerMode::Once,
-                ),
             },
-        });
+            EnemyKind::Lurker,
+            ArenaEntity,
+            params.cloned().unwrap_or_default(),
+        ));
     }
 }
 
@@ -97,45 +115,7 @@ fn lurk(mut query: Query<(&Enemy, &mut ExternalImpulse, &mut Lurker)>, time: Res
             lurker.timer.reset();
             lurker.step += 1;
             let mul: f32 = enemy.facing.into();
-            impulse.set_impulse(Vec2::new(20.0 * mul, 25.0));
-        }
-    }
-}
-
-fn health(
-    mut commands: Commands,
-    query: Query<(Entity, &Enemy, &Transform), (With<Lurker>, Changed<Enemy>)>,
-    texture_assets: Res<TextureAssets>,
-) {
-    for (entity, enemy, trans) in query.iter() {
-        if enemy.health <= 0 {
-            let _ = &commands.entity(entity).despawn();
-
-            // Spawn Explosion
-            commands.spawn(ExplosionBundle {
-                sprite_bundle: SpriteSheetBundle {
-                    atlas: TextureAtlas {
-                        layout: texture_assets.explosion_layout.clone(),
-                        index: 0,
-                    },
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(
-                            enemy.health.abs() as f32 * 2.0,
-                            enemy.health.abs() as f32 * 2.0,
-                        )),
-                        ..Default::default()
-                    },
-                    texture: texture_assets.explosion.clone(),
-                    transform: Transform::from_translation(trans.translation),
-                    ..Default::default()
-                },
-                collider: Collider::circle(enemy.health.abs() as f32),
-                explosion: Explosion {
-                    power: enemy.health.abs(),
-                    timer: Timer::from_seconds(0.5, TimerMode::Once),
-                },
-                ..Default::default()
-            });
+            impulse.set_impulse(Vec2::new(lurker.lunge.x * mul, lurker.lunge.y));
         }
     }
 }
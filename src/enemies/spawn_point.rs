@@ -0,0 +1,102 @@
+use crate::enemies::archetype::{roll, EnemyArchetype, EnemyArchetypes};
+use crate::enemies::SpawnParams;
+use crate::loading::GameData;
+use bevy::{prelude::*, reflect::TypePath, utils::HashMap};
+use rand::Rng;
+
+/// Half the camera's visible width, used until a camera reports its own and
+/// whenever there is none, e.g. headless
+const HALF_WIDTH: f32 = 15.0;
+
+/// Named places enemies can appear, keyed by name, e.g. `[sky]`
+#[derive(serde::Deserialize, Clone, TypePath, Asset)]
+pub struct SpawnPointTable {
+    #[serde(flatten)]
+    points: HashMap<String, SpawnPoint>,
+}
+
+/// An area an enemy spawns in, given for the left side and mirrored for the right
+#[derive(serde::Deserialize, Clone, Copy)]
+pub(crate) struct SpawnPoint {
+    /// Distance out past the edge of the screen, negative is on screen
+    pub x: [f32; 2],
+    pub y: [f32; 2],
+    /// Measure `x` from the middle of the arena instead of the edge
+    #[serde(default)]
+    pub centre: bool,
+}
+
+#[derive(Resource)]
+pub(crate) struct SpawnPoints {
+    points: HashMap<String, SpawnPoint>,
+    /// Half the visible width of the arena, off-screen offsets start here
+    pub half_width: f32,
+}
+
+impl Default for SpawnPoints {
+    fn default() -> Self {
+        Self {
+            points: HashMap::default(),
+            half_width: HALF_WIDTH,
+        }
+    }
+}
+
+impl SpawnPoints {
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.points.contains_key(name)
+    }
+
+    /// The position `params` already settled on, else one rolled in the point
+    /// it names, the archetype's own, or the archetype's `edge_offset` and `height`
+    pub(crate) fn position(
+        &self,
+        archetype: &EnemyArchetype,
+        params: Option<&SpawnParams>,
+        facing_mul: f32,
+        rng: &mut impl Rng,
+    ) -> Vec2 {
+        if let Some(position) = params.and_then(|p| p.position) {
+            return position;
+        }
+
+        let point = params
+            .and_then(|p| p.point.as_deref())
+            .or(archetype.spawn_point.as_deref())
+            .and_then(|name| self.points.get(name))
+            .copied()
+            .unwrap_or(SpawnPoint {
+                x: archetype.edge_offset,
+                y: archetype.height,
+                centre: false,
+            });
+
+        let edge = if point.centre { 0.0 } else { self.half_width };
+        Vec2::new(
+            (edge + roll(rng, point.x)) * -facing_mul,
+            roll(rng, point.y),
+        )
+    }
+}
+
+pub(crate) fn load_spawn_points(
+    mut spawn_points: ResMut<SpawnPoints>,
+    game_data: Res<GameData>,
+    tables: Res<Assets<SpawnPointTable>>,
+    archetypes: Option<Res<EnemyArchetypes>>,
+) {
+    if let Some(table) = tables.get(&game_data.spawn_points) {
+        spawn_points.points = table.points.clone();
+    }
+
+    let Some(archetypes) = archetypes else {
+        return;
+    };
+    for (kind, name, archetype) in archetypes.iter() {
+        if let Some(point) = &archetype.spawn_point {
+            if !spawn_points.contains(point) {
+                warn!("{kind:?} {name} spawns at unknown point {point:?}, using its own ranges");
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::enemies::archetype::EnemyArchetypes;
+use crate::enemies::spawn_point::SpawnPoints;
+use crate::enemies::{roll_facing, Enemy, EnemyKind, SpawnParams};
+use crate::rng::GameRng;
+use bevy::prelude::*;
+
+/// Holds a spawn marker back while its enemy is announced. The spawn systems
+/// skip markers that still have one
+#[derive(Component)]
+pub(crate) struct Telegraph {
+    pub kind: EnemyKind,
+    /// Set once the variant is known, so the spawner can budget for it
+    pub cost: f32,
+    timer: Timer,
+}
+
+impl Telegraph {
+    pub fn new(kind: EnemyKind) -> Self {
+        Self {
+            kind,
+            cost: 0.0,
+            timer: Timer::default(),
+        }
+    }
+}
+
+/// Sent `delay` seconds before an enemy appears at `position`
+#[derive(Event)]
+pub struct SpawnTelegraphed {
+    pub kind: EnemyKind,
+    pub position: Vec2,
+    pub size: Vec2,
+    pub delay: f32,
+}
+
+/// Sent once an enemy is in the arena, whatever spawned it
+#[derive(Event)]
+pub struct EnemySpawned {
+    pub entity: Entity,
+    pub kind: EnemyKind,
+    pub size: Vec2,
+}
+
+/// Settles everything the warning needs to show up front: variant, side and
+/// position. The spawn systems then use what's in the params
+pub(crate) fn announce_spawns(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Telegraph, &mut SpawnParams), Added<Telegraph>>,
+    archetypes: Res<EnemyArchetypes>,
+    spawn_points: Res<SpawnPoints>,
+    mut game_rng: ResMut<GameRng>,
+    mut telegraphed: EventWriter<SpawnTelegraphed>,
+) {
+    for (entity, mut telegraph, mut params) in query.iter_mut() {
+        let rng = &mut game_rng.spawning;
+        let kind = telegraph.kind;
+
+        let named = params
+            .variant
+            .clone()
+            .and_then(|variant| Some((variant.clone(), archetypes.variant(kind, &variant)?)));
+        let picked = named.or_else(|| {
+            archetypes
+                .pick_named(kind, rng)
+                .map(|(variant, archetype)| (variant.to_string(), archetype))
+        });
+        // Already reported when the archetypes loaded
+        let Some((variant, archetype)) = picked else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let facing = roll_facing(Some(&*params), rng);
+        let position = spawn_points.position(archetype, Some(&*params), facing.into(), rng);
+
+        params.variant = Some(variant);
+        params.facing = Some(facing);
+        params.position = Some(position);
+        telegraph.cost = archetype.cost;
+        telegraph.timer = Timer::from_seconds(archetype.telegraph, TimerMode::Once);
+
+        telegraphed.send(SpawnTelegraphed {
+            kind,
+            position,
+            size: archetype.size(),
+            delay: archetype.telegraph,
+        });
+    }
+}
+
+pub(crate) fn tick_telegraphs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Telegraph)>,
+) {
+    for (entity, mut telegraph) in query.iter_mut() {
+        if telegraph.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Telegraph>();
+        }
+    }
+}
+
+pub(crate) fn announce_arrivals(
+    query: Query<(Entity, &EnemyKind, &Sprite), Added<Enemy>>,
+    mut spawned: EventWriter<EnemySpawned>,
+) {
+    for (entity, kind, sprite) in query.iter() {
+        spawned.send(EnemySpawned {
+            entity,
+            kind: *kind,
+            size: sprite.custom_size.unwrap_or(Vec2::ONE),
+        });
+    }
+}
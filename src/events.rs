@@ -1,13 +1,30 @@
-use crate::{enemies::SpawnRates, loading::GameData, GameState};
-use bevy::{prelude::*, reflect::TypePath, time::Stopwatch, utils::HashMap};
+use crate::{
+    difficulty::{DifficultySettings, Preset},
+    enemies::{EnemyKind, SpawnRates},
+    loading::GameData,
+    replay::{Playback, Recording},
+    running, GameState,
+};
+use bevy::{prelude::*, reflect::TypePath, time::Stopwatch};
+use std::{collections::BTreeMap, error::Error, fmt};
 
 pub struct EventsPlugin;
 
 impl Plugin for EventsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<EnemySpawnsChanged>()
-            .add_systems(OnEnter(GameState::Menu), load)
-            .add_systems(Update, update.run_if(in_state(GameState::Playing)));
+            .add_systems(OnExit(GameState::Loading), report_spawn_table)
+            .init_resource::<Threat>()
+            .init_resource::<SpawnRatesOverTime>()
+            .add_systems(OnEnter(GameState::Playing), load)
+            .add_systems(Update, update.run_if(running))
+            .add_systems(
+                Update,
+                reload_spawn_table
+                    .before(update)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<Playback>)),
+            );
     }
 }
 
@@ -21,113 +38,743 @@ pub struct EnemySpawnsChanged {
     pub diver: Option<f32>,
     pub giant: Option<f32>,
     pub behemoth: Option<f32>,
+    pub from_left: Option<f32>,
+    pub budget: Option<f32>,
+    pub caps: BTreeMap<EnemyKind, u32>,
 }
 
-#[derive(serde::Deserialize, Clone, TypePath, Asset)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, TypePath, Asset)]
 // #[uuid = "c2609287-9672-4cb8-b95d-afb0a8df2200"]
 pub struct TimeTable {
-    pub t: toml::value::Table,
+    pub t: BTreeMap<String, SpawnKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endless: Option<Endless>,
 }
 
-#[derive(Resource, Default)]
-struct SpawnRatesOverTime {
-    table: HashMap<String, SpawnRates>,
+/// How the run keeps getting harder once it passes the last key. Every
+/// `level_time` seconds past it is one more threat level, and levels are
+/// fractional so everything grows smoothly
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Endless {
+    pub level_time: f32,
+    /// Both spawn times are multiplied by this once per level...
+    pub interval_scale: f32,
+    /// ...but never drop below these
+    pub min_spawn_floor: f32,
+    pub max_spawn_floor: f32,
+    /// Weight added per level, as a share of the last key's total weight
+    pub weights: BTreeMap<EnemyKind, f32>,
+    /// Fraction of their base health and speed enemies gain per level
+    pub health: f32,
+    pub speed: f32,
+    /// Added to the spawn budget per level
+    pub budget: f32,
 }
 
-impl SpawnRatesOverTime {
-    fn new() -> Self {
-        Self { ..default() }
+impl Default for Endless {
+    fn default() -> Self {
+        Self {
+            level_time: 30.0,
+            interval_scale: 0.9,
+            min_spawn_floor: 0.3,
+            max_spawn_floor: 0.8,
+            weights: BTreeMap::new(),
+            health: 0.0,
+            speed: 0.0,
+            budget: 0.0,
+        }
     }
 }
 
-impl From<TimeTable> for SpawnRatesOverTime {
-    fn from(time_table: TimeTable) -> Self {
-        let mut result = Self::new();
-        for t in time_table.t.iter() {
-            result.table.insert(t.0.to_owned(), t.1.into());
+impl Endless {
+    fn validate(&self) -> Vec<SpawnTableError> {
+        let mut errors = Vec::new();
+        let mut check = |field: String, value: f32, ok: bool, expected: &'static str| {
+            if !ok {
+                errors.push(SpawnTableError::Endless {
+                    field,
+                    value,
+                    expected,
+                });
+            }
+        };
+
+        check(
+            "level_time".into(),
+            self.level_time,
+            self.level_time > 0.0,
+            "above 0",
+        );
+        check(
+            "interval_scale".into(),
+            self.interval_scale,
+            self.interval_scale > 0.0 && self.interval_scale <= 1.0,
+            "above 0 and at most 1",
+        );
+        check(
+            "min_spawn_floor".into(),
+            self.min_spawn_floor,
+            self.min_spawn_floor > 0.0,
+            "above 0",
+        );
+        check(
+            "max_spawn_floor".into(),
+            self.max_spawn_floor,
+            self.max_spawn_floor >= self.min_spawn_floor,
+            "at least min_spawn_floor",
+        );
+        for (kind, weight) in self.weights.iter() {
+            check(
+                format!("weights.{}", format!("{kind:?}").to_lowercase()),
+                *weight,
+                *weight >= 0.0,
+                "at least 0",
+            );
+        }
+        check(
+            "health".into(),
+            self.health,
+            self.health >= 0.0,
+            "at least 0",
+        );
+        check("speed".into(), self.speed, self.speed >= 0.0, "at least 0");
+        check(
+            "budget".into(),
+            self.budget,
+            self.budget >= 0.0,
+            "at least 0",
+        );
+
+        errors
+    }
+
+    /// `rates` from the last key, escalated by `level`
+    fn escalate(&self, rates: &SpawnRates, level: f32) -> SpawnRates {
+        let mut rates = rates.clone();
+        let scale = self.interval_scale.powf(level);
+        let min = (rates.min_spawn_time.unwrap_or_default() * scale).max(self.min_spawn_floor);
+        let max = (rates.max_spawn_time.unwrap_or_default() * scale).max(self.max_spawn_floor);
+        rates.min_spawn_time = Some(min);
+        rates.max_spawn_time = Some(max.max(min));
+        rates.budget = rates.budget.map(|budget| budget + self.budget * level);
+
+        // Keyframe weights are already normalised, so they add up to 1
+        for (kind, weight) in self.weights.iter() {
+            let current = rates.weight_mut(*kind);
+            *current = Some(current.unwrap_or_default() + weight * level);
         }
 
-        result
+        rates.normalized()
     }
 }
 
-impl From<&toml::Value> for SpawnRates {
-    fn from(value: &toml::Value) -> Self {
-        let mut result = SpawnRates::default();
-        if let Some(val) = value.get("min_spawn_time") {
-            result.min_spawn_time = Some(val.as_float().unwrap() as f32);
+/// How far into endless escalation the run is, read by enemies as they spawn.
+/// The health multiplier includes the difficulty's
+#[derive(Resource)]
+pub struct Threat {
+    /// Levels past the last key, 0 until the run gets there
+    pub level: f32,
+    /// Multipliers for base enemy health and speed
+    pub health: f32,
+    pub speed: f32,
+}
+
+impl Default for Threat {
+    fn default() -> Self {
+        Self {
+            level: 0.0,
+            health: 1.0,
+            speed: 1.0,
         }
-        if let Some(val) = value.get("max_spawn_time") {
-            result.max_spawn_time = Some(val.as_float().unwrap() as f32);
+    }
+}
+
+/// One `[t.<seconds>]` entry of the spawn table
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct SpawnKey {
+    #[serde(flatten)]
+    pub rates: SpawnRates,
+    #[serde(default, skip_serializing_if = "Blend::is_step")]
+    pub blend: Blend,
+    /// Anything else, kept so it can be reported instead of failing the load
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+/// A problem with one key of the spawn table
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnTableError {
+    /// The key isn't a time in seconds
+    BadTime { key: String },
+    /// A field that is neither an enemy nor a setting, most likely a typo
+    UnknownField { key: String, field: String },
+    NegativeWeight {
+        key: String,
+        enemy: &'static str,
+        weight: f32,
+    },
+    /// `from_left` is a chance, so it has to be between 0 and 1
+    SideBias { key: String, from_left: f32 },
+    /// Nothing would ever fit in a budget this small
+    Budget { key: String, budget: f32 },
+    /// The spawn times, after carrying over from earlier keys, leave no range
+    /// to pick from
+    SpawnTimes {
+        key: String,
+        min: Option<f32>,
+        max: Option<f32>,
+    },
+    /// An `[endless]` setting out of range, escalation is turned off
+    Endless {
+        field: String,
+        value: f32,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for SpawnTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnTableError::BadTime { key } => {
+                write!(f, "[t.{key}] key must be a time in seconds")
+            }
+            SpawnTableError::UnknownField { key, field } => {
+                write!(f, "[t.{key}] unknown enemy or field `{field}`")
+            }
+            SpawnTableError::NegativeWeight { key, enemy, weight } => {
+                write!(
+                    f,
+                    "[t.{key}] {enemy} weight is {weight}, weights can't be negative"
+                )
+            }
+            SpawnTableError::SideBias { key, from_left } => {
+                write!(f, "[t.{key}] from_left is {from_left}, it should be 0 to 1")
+            }
+            SpawnTableError::Budget { key, budget } => {
+                write!(f, "[t.{key}] budget is {budget}, it has to be above 0")
+            }
+            SpawnTableError::SpawnTimes { key, min, max } => write!(
+                f,
+                "[t.{key}] needs 0 < min_spawn_time <= max_spawn_time, got {min:?} and {max:?}"
+            ),
+            SpawnTableError::Endless {
+                field,
+                value,
+                expected,
+            } => write!(f, "[endless] {field} is {value}, it should be {expected}"),
         }
-        if let Some(val) = value.get("hopper") {
-            result.hopper = Some(val.as_integer().unwrap() as f32);
+    }
+}
+
+impl Error for SpawnTableError {}
+
+impl SpawnKey {
+    fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut fields = vec![
+            (
+                "min_spawn_time",
+                self.rates.min_spawn_time.map(|t| t.to_string()),
+            ),
+            (
+                "max_spawn_time",
+                self.rates.max_spawn_time.map(|t| t.to_string()),
+            ),
+        ];
+        fields.extend(
+            self.rates
+                .weights()
+                .into_iter()
+                .map(|(enemy, weight)| (enemy, weight.map(|w| w.to_string()))),
+        );
+        fields.push(("from_left", self.rates.from_left.map(|p| p.to_string())));
+        fields.push(("budget", self.rates.budget.map(|b| b.to_string())));
+        fields.push((
+            "caps",
+            (!self.rates.caps.is_empty()).then(|| format!("{:?}", self.rates.caps)),
+        ));
+        fields.push(("blend", Some(format!("{:?}", self.blend).to_lowercase())));
+        fields
+    }
+}
+
+impl TimeTable {
+    /// One line per key added or removed and per field changed
+    fn diff(&self, new: &TimeTable) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for (key, old_key) in self.t.iter() {
+            let Some(new_key) = new.t.get(key) else {
+                lines.push(format!("[t.{key}] removed"));
+                continue;
+            };
+
+            for ((field, old), (_, new)) in old_key.fields().into_iter().zip(new_key.fields()) {
+                if old != new {
+                    lines.push(format!(
+                        "[t.{key}] {field}: {} -> {}",
+                        old.as_deref().unwrap_or("-"),
+                        new.as_deref().unwrap_or("-")
+                    ));
+                }
+            }
         }
-        if let Some(val) = value.get("climber") {
-            result.climber = Some(val.as_integer().unwrap() as f32);
+
+        for key in new.t.keys().filter(|key| !self.t.contains_key(*key)) {
+            lines.push(format!("[t.{key}] added"));
         }
-        if let Some(val) = value.get("lurker") {
-            result.lurker = Some(val.as_integer().unwrap() as f32);
+
+        if self.endless != new.endless {
+            lines.push("[endless] changed".into());
         }
-        if let Some(val) = value.get("diver") {
-            result.diver = Some(val.as_integer().unwrap() as f32);
+
+        lines
+    }
+
+    /// Every problem with the table. Keys with problems are left out of the
+    /// timeline, apart from unknown fields which are just ignored
+    pub fn validate(&self) -> Vec<SpawnTableError> {
+        let mut errors = self.resolve().1;
+        if let Some(endless) = &self.endless {
+            errors.extend(endless.validate());
         }
-        if let Some(val) = value.get("giant") {
-            result.giant = Some(val.as_integer().unwrap() as f32);
+        errors
+    }
+
+    fn resolve(&self) -> (Vec<Keyframe>, Vec<SpawnTableError>) {
+        let mut errors = Vec::new();
+        let mut keys = Vec::new();
+
+        for (key, entry) in self.t.iter() {
+            let time = match key.parse::<f32>() {
+                Ok(time) if time.is_finite() && time >= 0.0 => time,
+                _ => {
+                    errors.push(SpawnTableError::BadTime { key: key.clone() });
+                    continue;
+                }
+            };
+
+            errors.extend(
+                entry
+                    .unknown
+                    .keys()
+                    .map(|field| SpawnTableError::UnknownField {
+                        key: key.clone(),
+                        field: field.clone(),
+                    }),
+            );
+
+            let negative = entry
+                .rates
+                .weights()
+                .into_iter()
+                .filter_map(|(enemy, weight)| {
+                    let weight = weight?;
+                    // Written this way round so NaN is caught too
+                    (!(weight >= 0.0)).then(|| SpawnTableError::NegativeWeight {
+                        key: key.clone(),
+                        enemy,
+                        weight,
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !negative.is_empty() {
+                errors.extend(negative);
+                continue;
+            }
+            if let Some(from_left) = entry.rates.from_left {
+                if !(0.0..=1.0).contains(&from_left) {
+                    errors.push(SpawnTableError::SideBias {
+                        key: key.clone(),
+                        from_left,
+                    });
+                    continue;
+                }
+            }
+            if let Some(budget) = entry.rates.budget {
+                if !(budget > 0.0) {
+                    errors.push(SpawnTableError::Budget {
+                        key: key.clone(),
+                        budget,
+                    });
+                    continue;
+                }
+            }
+
+            keys.push((
+                key,
+                Keyframe {
+                    time,
+                    rates: entry.rates.clone(),
+                    blend: entry.blend,
+                },
+            ));
         }
-        if let Some(val) = value.get("behemoth") {
-            result.behemoth = Some(val.as_integer().unwrap() as f32);
+        keys.sort_by(|(_, a), (_, b)| a.time.total_cmp(&b.time));
+
+        // Anything a key leaves out carries over from the keys before it
+        let mut carried = SpawnRates::default();
+        let mut timeline = Vec::new();
+        for (key, mut keyframe) in keys {
+            let mut rates = carried.clone();
+            rates.merge(&keyframe.rates);
+
+            match (rates.min_spawn_time, rates.max_spawn_time) {
+                (Some(min), Some(max)) if min > 0.0 && min <= max => {}
+                (min, max) => {
+                    errors.push(SpawnTableError::SpawnTimes {
+                        key: key.clone(),
+                        min,
+                        max,
+                    });
+                    continue;
+                }
+            }
+
+            keyframe.rates = rates.normalized();
+            carried = rates;
+            timeline.push(keyframe);
         }
 
-        let total_chance = result.all();
+        (timeline, errors)
+    }
+}
+
+/// How a key's rates lead into the next key's
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend {
+    /// Hold until the next key, then jump straight to it
+    #[default]
+    Step,
+    Linear,
+    /// Smoothstep, gentle at both ends
+    Ease,
+}
 
-        result.hopper = Some(result.hopper.unwrap() / total_chance);
-        result.climber = Some(result.climber.unwrap() / total_chance);
-        result.lurker = Some(result.lurker.unwrap() / total_chance);
-        result.diver = Some(result.diver.unwrap() / total_chance);
-        result.giant = Some(result.giant.unwrap() / total_chance);
-        result.behemoth = Some(result.behemoth.unwrap() / total_chance);
+impl Blend {
+    fn is_step(&self) -> bool {
+        *self == Blend::Step
+    }
 
-        result
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Blend::Step => 0.0,
+            Blend::Linear => t,
+            Blend::Ease => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+struct Keyframe {
+    time: f32,
+    rates: SpawnRates,
+    blend: Blend,
+}
+
+/// The spawn table as a timeline. Keys are sorted by time, and each one has
+/// every field filled in, with weights normalised so any two can be blended
+#[derive(Resource, Default)]
+pub(crate) struct SpawnRatesOverTime {
+    keys: Vec<Keyframe>,
+    /// Index of the first key not reached yet
+    next: usize,
+    /// Time into the run, a fresh table starts a fresh clock
+    elapsed: Stopwatch,
+    /// Only set if the table's `[endless]` settings are valid
+    endless: Option<Endless>,
+    /// What the keys were built from, kept to diff against on reload
+    source: TimeTable,
+}
+
+impl SpawnRatesOverTime {
+    /// Rates at `time`, from the last key reached. `None` before the first key
+    fn sample(&self, time: f32) -> Option<SpawnRates> {
+        let current = self.next.checked_sub(1)?;
+        let key = &self.keys[current];
+
+        match (self.keys.get(current + 1), &self.endless) {
+            (Some(next), _) if key.blend != Blend::Step => {
+                let t = ((time - key.time) / (next.time - key.time)).clamp(0.0, 1.0);
+                Some(key.rates.lerp(&next.rates, key.blend.apply(t)))
+            }
+            (None, Some(endless)) => Some(endless.escalate(&key.rates, self.level(time))),
+            _ => Some(key.rates.clone()),
+        }
+    }
+
+    /// Threat levels past the last key
+    fn level(&self, time: f32) -> f32 {
+        match (self.keys.last(), &self.endless) {
+            (Some(last), Some(endless)) => ((time - last.time) / endless.level_time).max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    fn escalating(&self) -> bool {
+        self.endless.is_some() && self.next > 0 && self.next == self.keys.len()
+    }
+
+    fn threat(&self, time: f32, preset: &Preset) -> Threat {
+        let level = self.level(time);
+        let (health, speed) = self
+            .endless
+            .as_ref()
+            .map_or((0.0, 0.0), |endless| (endless.health, endless.speed));
+
+        Threat {
+            level,
+            health: preset.enemy_health * (1.0 + health * level),
+            speed: 1.0 + speed * level,
+        }
+    }
+
+    /// Whether the rates move between keys right now
+    fn blending(&self) -> bool {
+        self.next > 0
+            && self.next < self.keys.len()
+            && self.keys[self.next - 1].blend != Blend::Step
+    }
+}
+
+impl From<TimeTable> for SpawnRatesOverTime {
+    fn from(time_table: TimeTable) -> Self {
+        let endless = time_table
+            .endless
+            .clone()
+            .filter(|endless| endless.validate().is_empty());
+
+        Self {
+            keys: time_table.resolve().0,
+            endless,
+            source: time_table,
+            ..default()
+        }
+    }
+}
+
+impl SpawnRates {
+    fn weight_mut(&mut self, kind: EnemyKind) -> &mut Option<f32> {
+        match kind {
+            EnemyKind::Hopper => &mut self.hopper,
+            EnemyKind::Climber => &mut self.climber,
+            EnemyKind::Lurker => &mut self.lurker,
+            EnemyKind::Diver => &mut self.diver,
+            EnemyKind::Giant => &mut self.giant,
+            EnemyKind::Behemoth => &mut self.behemoth,
+        }
+    }
+
+    fn weights(&self) -> [(&'static str, Option<f32>); 6] {
+        [
+            ("hopper", self.hopper),
+            ("climber", self.climber),
+            ("lurker", self.lurker),
+            ("diver", self.diver),
+            ("giant", self.giant),
+            ("behemoth", self.behemoth),
+        ]
+    }
+
+    fn merge(&mut self, other: &SpawnRates) {
+        self.min_spawn_time = other.min_spawn_time.or(self.min_spawn_time);
+        self.max_spawn_time = other.max_spawn_time.or(self.max_spawn_time);
+        self.hopper = other.hopper.or(self.hopper);
+        self.climber = other.climber.or(self.climber);
+        self.lurker = other.lurker.or(self.lurker);
+        self.diver = other.diver.or(self.diver);
+        self.giant = other.giant.or(self.giant);
+        self.behemoth = other.behemoth.or(self.behemoth);
+        self.from_left = other.from_left.or(self.from_left);
+        self.budget = other.budget.or(self.budget);
+        self.caps.extend(other.caps.clone());
+    }
+
+    /// Weights as fractions of their total
+    fn normalized(&self) -> SpawnRates {
+        let total = self.all();
+        let share = |weight: Option<f32>| {
+            Some(if total > 0.0 {
+                weight.unwrap_or_default() / total
+            } else {
+                0.0
+            })
+        };
+
+        SpawnRates {
+            min_spawn_time: self.min_spawn_time,
+            max_spawn_time: self.max_spawn_time,
+            hopper: share(self.hopper),
+            climber: share(self.climber),
+            lurker: share(self.lurker),
+            diver: share(self.diver),
+            giant: share(self.giant),
+            behemoth: share(self.behemoth),
+            from_left: self.from_left,
+            budget: self.budget,
+            caps: self.caps.clone(),
+        }
+    }
+
+    fn lerp(&self, other: &SpawnRates, t: f32) -> SpawnRates {
+        let mix = |a: Option<f32>, b: Option<f32>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + (b - a) * t),
+            _ => a.or(b),
+        };
+
+        SpawnRates {
+            min_spawn_time: mix(self.min_spawn_time, other.min_spawn_time),
+            max_spawn_time: mix(self.max_spawn_time, other.max_spawn_time),
+            hopper: mix(self.hopper, other.hopper),
+            climber: mix(self.climber, other.climber),
+            lurker: mix(self.lurker, other.lurker),
+            diver: mix(self.diver, other.diver),
+            giant: mix(self.giant, other.giant),
+            behemoth: mix(self.behemoth, other.behemoth),
+            from_left: mix(self.from_left, other.from_left),
+            budget: mix(self.budget, other.budget),
+            // Caps are whole enemies, they switch over at the next key
+            caps: self.caps.clone(),
+        }
+    }
+}
+
+impl From<SpawnRates> for EnemySpawnsChanged {
+    fn from(rates: SpawnRates) -> Self {
+        EnemySpawnsChanged {
+            min_spawn_time: rates.min_spawn_time,
+            max_spawn_time: rates.max_spawn_time,
+            hopper: rates.hopper,
+            climber: rates.climber,
+            lurker: rates.lurker,
+            diver: rates.diver,
+            giant: rates.giant,
+            behemoth: rates.behemoth,
+            from_left: rates.from_left,
+            budget: rates.budget,
+            caps: rates.caps,
+        }
+    }
+}
+
+/// Logs everything wrong with the spawn tables once they have loaded, the game
+/// carries on without the broken keys
+fn report_spawn_table(game_data: Res<GameData>, spawn_rates_asset: Res<Assets<TimeTable>>) {
+    for (path, handle) in game_data.spawn_tables.iter() {
+        let Some(time_table) = spawn_rates_asset.get(handle) else {
+            continue;
+        };
+
+        for error in time_table.validate() {
+            error!("{path}: {error}");
+        }
+    }
+}
+
+/// Swaps in an edited spawn table mid-run, keeping the run's clock, and
+/// applies whatever it says for the current moment straight away
+fn reload_spawn_table(
+    asset_server: Res<AssetServer>,
+    difficulty: Res<DifficultySettings>,
+    spawn_rates_asset: Res<Assets<TimeTable>>,
+    mut asset_events: EventReader<AssetEvent<TimeTable>>,
+    mut spawn_rates_over_time: ResMut<SpawnRatesOverTime>,
+    mut ev_writer: EventWriter<EnemySpawnsChanged>,
+    recording: Option<Res<Recording>>,
+) {
+    let modified = asset_events.read().fold(false, |modified, event| {
+        modified || event.is_modified(&difficulty.spawn_rates)
+    });
+    if !modified {
+        return;
+    }
+    let Some(time_table) = spawn_rates_asset.get(&difficulty.spawn_rates) else {
+        return;
+    };
+
+    let path = asset_server
+        .get_path(&difficulty.spawn_rates)
+        .map(|path| path.to_string())
+        .unwrap_or_default();
+    let changes = spawn_rates_over_time.source.diff(time_table);
+    if changes.is_empty() {
+        return;
+    }
+    info!("Reloaded {path}:\n  {}", changes.join("\n  "));
+    for error in time_table.validate() {
+        error!("{path}: {error}");
+    }
+    if recording.is_some() {
+        warn!("The replay of this run keeps the spawn table it started with, it won't play back the same");
+    }
+
+    let elapsed = spawn_rates_over_time.elapsed.clone();
+    let now = elapsed.elapsed_secs();
+    *spawn_rates_over_time = SpawnRatesOverTime::from(time_table.to_owned());
+    spawn_rates_over_time.elapsed = elapsed;
+    spawn_rates_over_time.next = spawn_rates_over_time
+        .keys
+        .iter()
+        .take_while(|key| key.time <= now)
+        .count();
+
+    if let Some(rates) = spawn_rates_over_time.sample(now) {
+        ev_writer.send(rates.into());
     }
 }
 
 pub fn load(
     mut commands: Commands,
-    game_data: Res<GameData>,
+    difficulty: Res<DifficultySettings>,
     spawn_rates_asset: Res<Assets<TimeTable>>,
 ) {
-    if let Some(spawn_rates_time_table) = spawn_rates_asset.get(&game_data.spawn_rates) {
-        commands.insert_resource(SpawnRatesOverTime::from(spawn_rates_time_table.to_owned()));
-    }
+    let spawn_rates_over_time = match spawn_rates_asset.get(&difficulty.spawn_rates) {
+        Some(spawn_rates_time_table) => SpawnRatesOverTime::from(spawn_rates_time_table.to_owned()),
+        None => {
+            error!(
+                "No spawn table loaded for {:?}, nothing will spawn at random",
+                difficulty.difficulty
+            );
+            SpawnRatesOverTime::default()
+        }
+    };
+    commands.insert_resource(spawn_rates_over_time);
+    commands.insert_resource(Threat {
+        health: difficulty.preset.enemy_health,
+        ..default()
+    });
 }
 
 fn update(
     time: Res<Time>,
-    mut current_time: Local<Stopwatch>,
+    difficulty: Res<DifficultySettings>,
     mut spawn_rates_over_time: ResMut<SpawnRatesOverTime>,
+    mut threat: ResMut<Threat>,
     mut ev_writer: EventWriter<EnemySpawnsChanged>,
 ) {
-    current_time.tick(time.delta());
-    let spawn_table = spawn_rates_over_time.table.clone();
-    let mut remove_key = "";
-    for entry in spawn_table.iter() {
-        if current_time.elapsed().as_secs() == entry.0.parse::<u64>().unwrap() {
-            remove_key = entry.0;
-            ev_writer.send(EnemySpawnsChanged {
-                min_spawn_time: entry.1.min_spawn_time,
-                max_spawn_time: entry.1.max_spawn_time,
-                hopper: entry.1.hopper,
-                climber: entry.1.climber,
-                lurker: entry.1.lurker,
-                diver: entry.1.diver,
-                giant: entry.1.giant,
-                behemoth: entry.1.behemoth,
-            });
-        }
-    }
-
-    if spawn_rates_over_time.table.contains_key(remove_key) {
-        spawn_rates_over_time.table.remove(remove_key);
+    spawn_rates_over_time.elapsed.tick(time.delta());
+    let now = spawn_rates_over_time.elapsed.elapsed_secs();
+
+    // A long frame can pass several keys at once, none of them are skipped
+    let mut reached = false;
+    while let Some(key) = spawn_rates_over_time.keys.get(spawn_rates_over_time.next) {
+        if key.time > now {
+            break;
+        }
+        spawn_rates_over_time.next += 1;
+        reached = true;
+    }
+
+    let escalating = spawn_rates_over_time.escalating();
+    if escalating {
+        *threat = spawn_rates_over_time.threat(now, &difficulty.preset);
+    }
+
+    if !reached && !escalating && !spawn_rates_over_time.blending() {
+        return;
+    }
+
+    if let Some(rates) = spawn_rates_over_time.sample(now) {
+        ev_writer.send(rates.into());
     }
 }
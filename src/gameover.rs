@@ -0,0 +1,228 @@
+use crate::difficulty::DifficultySettings;
+use crate::enemies::EnemyKind;
+use crate::highscores::HighScores;
+use crate::loading::FontAssets;
+use crate::player::Player;
+use crate::score::{RunStats, Score};
+use crate::GameState;
+use bevy::prelude::*;
+
+/// Game speed while the player's death plays out
+const SLOW_MO_SPEED: f32 = 0.2;
+/// Real seconds of slow motion before the results come up
+const SLOW_MO_TIME: f32 = 1.5;
+
+const BUTTON_NORMAL: Color = Color::rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::rgb(0.25, 0.25, 0.25);
+
+#[derive(Resource)]
+struct DeathTimer(Timer);
+
+#[derive(Component)]
+struct ResultsScreen;
+
+#[derive(Component, Clone, Copy)]
+enum ResultsButton {
+    Retry,
+    MainMenu,
+}
+
+pub struct GameOverPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameOver), start_slow_mo)
+            .add_systems(
+                Update,
+                death_animation.run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnExit(GameState::GameOver), end_slow_mo);
+
+        if !self.headless {
+            app.add_systems(
+                Update,
+                (show_results.after(death_animation), click_results_button)
+                    .run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnExit(GameState::GameOver), cleanup_results);
+        }
+    }
+}
+
+fn start_slow_mo(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(SLOW_MO_SPEED);
+    commands.insert_resource(DeathTimer(Timer::from_seconds(
+        SLOW_MO_TIME,
+        TimerMode::Once,
+    )));
+}
+
+/// The player swells and fades while the world keeps drifting in slow motion,
+/// then everything freezes behind the results
+fn death_animation(
+    real_time: Res<Time<Real>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut death_timer: ResMut<DeathTimer>,
+    mut query: Query<(&mut Transform, &mut Sprite), With<Player>>,
+) {
+    death_timer.0.tick(real_time.delta());
+    let t = death_timer.0.fraction();
+
+    for (mut transform, mut sprite) in query.iter_mut() {
+        transform.scale = Vec3::splat(1.0 + t * 2.0);
+        sprite.color.set_a(1.0 - t);
+    }
+
+    if death_timer.0.just_finished() {
+        time.pause();
+    }
+}
+
+fn end_slow_mo(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    commands.remove_resource::<DeathTimer>();
+    time.set_relative_speed(1.0);
+    time.unpause();
+}
+
+fn show_results(
+    mut commands: Commands,
+    death_timer: Res<DeathTimer>,
+    font_assets: Res<FontAssets>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    difficulty: Res<DifficultySettings>,
+    high_scores: Res<HighScores>,
+) {
+    if !death_timer.0.just_finished() {
+        return;
+    }
+
+    let style = |font_size: f32| TextStyle {
+        font: font_assets.fantasque_sans.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    let kills = EnemyKind::ALL
+        .iter()
+        .filter_map(|kind| {
+            let count = score.kills.get(kind).copied().unwrap_or_default();
+            (count > 0).then(|| format!("{kind:?} {count}"))
+        })
+        .collect::<Vec<_>>();
+
+    let lines = [
+        format!("Survived {:.1}s", stats.survived),
+        format!("Score {}", score.points),
+        format!("Best chain x{}", score.best_chain + 1),
+        format!(
+            "Kills {}{}",
+            score.total_kills(),
+            if kills.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", kills.join(", "))
+            }
+        ),
+        format!(
+            "Accuracy {:.0}% ({}/{})",
+            stats.accuracy() * 100.0,
+            stats.shots_hit,
+            stats.shots_fired
+        ),
+        format!(
+            "Best on {:?} {}",
+            difficulty.difficulty,
+            high_scores
+                .best(difficulty.difficulty)
+                .map_or(score.points, |best| best.points)
+        ),
+    ];
+
+    commands
+        .spawn((
+            ResultsScreen,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::BLACK.with_a(0.6)),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Game Over", style(56.0)));
+
+            for line in lines {
+                parent.spawn(TextBundle::from_section(line, style(28.0)));
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(16.0),
+                        margin: UiRect::top(Val::Px(24.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (ResultsButton::Retry, "Retry"),
+                        (ResultsButton::MainMenu, "Main Menu"),
+                    ] {
+                        parent
+                            .spawn((
+                                button,
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(200.0),
+                                        height: Val::Px(50.0),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..Default::default()
+                                    },
+                                    background_color: BUTTON_NORMAL.into(),
+                                    ..Default::default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(label, style(32.0)));
+                            });
+                    }
+                });
+        });
+}
+
+fn click_results_button(
+    mut state: ResMut<NextState<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &ResultsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => state.set(match button {
+                ResultsButton::Retry => GameState::Playing,
+                ResultsButton::MainMenu => GameState::Menu,
+            }),
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn cleanup_results(mut commands: Commands, query: Query<Entity, With<ResultsScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
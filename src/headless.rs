@@ -0,0 +1,132 @@
+use crate::actions::{ActionSet, Actions};
+use crate::enemies::telegraph::EnemySpawned;
+use crate::enemies::{Enemy, EnemyDied};
+use crate::player::PLAYER_CENTER;
+use crate::replay::Playback;
+use crate::{running, GameState};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::time::Duration;
+
+/// Present when the game runs without a window, camera or renderer
+#[derive(Resource)]
+pub(crate) struct Headless;
+
+/// Fixed time every update advances the game by, from `GamePlugin::step`
+#[derive(Resource, Clone, Copy)]
+pub(crate) struct SimulationStep(pub Duration);
+
+/// Totals over every run since the app started, for checking what a long
+/// simulation got up to
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct SimulationStats {
+    pub runs: u32,
+    pub spawned: u32,
+    pub died: u32,
+}
+
+/// Drives `Actions` when there are no devices to read: aims at the closest
+/// enemy, charges for `charge` seconds, releases, then waits `cooldown` seconds
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct SimulatedInput {
+    pub charge: f32,
+    pub cooldown: f32,
+}
+
+impl Default for SimulatedInput {
+    fn default() -> Self {
+        Self {
+            charge: 0.6,
+            cooldown: 0.3,
+        }
+    }
+}
+
+#[derive(Default)]
+enum Trigger {
+    #[default]
+    Idle,
+    Charging,
+}
+
+pub struct HeadlessPlugin {
+    pub step: Option<Duration>,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Headless)
+            .init_resource::<SimulatedInput>()
+            .init_resource::<SimulationStats>()
+            .add_systems(OnEnter(GameState::Menu), skip_menu)
+            .add_systems(OnEnter(GameState::GameOver), skip_menu)
+            .add_systems(OnEnter(GameState::Playing), count_run)
+            .add_systems(
+                Update,
+                simulate_input
+                    .in_set(ActionSet::Read)
+                    .run_if(running)
+                    .run_if(not(resource_exists::<Playback>)),
+            )
+            .add_systems(Update, count_enemies);
+
+        if let Some(step) = self.step {
+            app.insert_resource(SimulationStep(step))
+                .insert_resource(TimeUpdateStrategy::ManualDuration(step));
+        }
+    }
+}
+
+/// There's nobody to click Play or Retry, so every run starts right away
+fn skip_menu(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
+
+fn count_run(mut stats: ResMut<SimulationStats>) {
+    stats.runs += 1;
+}
+
+fn count_enemies(
+    mut spawned: EventReader<EnemySpawned>,
+    mut died: EventReader<EnemyDied>,
+    mut stats: ResMut<SimulationStats>,
+) {
+    stats.spawned += spawned.read().count() as u32;
+    stats.died += died.read().count() as u32;
+}
+
+fn simulate_input(
+    settings: Res<SimulatedInput>,
+    time: Res<Time>,
+    query: Query<&Transform, With<Enemy>>,
+    mut actions: ResMut<Actions>,
+    mut trigger: Local<Trigger>,
+    mut timer: Local<f32>,
+) {
+    if let Some(target) = query
+        .iter()
+        .map(|t| t.translation.truncate())
+        .min_by(|a, b| {
+            a.distance_squared(PLAYER_CENTER)
+                .total_cmp(&b.distance_squared(PLAYER_CENTER))
+        })
+    {
+        actions.aim = target;
+    }
+
+    *timer += time.delta_seconds();
+
+    match *trigger {
+        Trigger::Idle if *timer >= settings.cooldown && !query.is_empty() => {
+            actions.charge = true;
+            *trigger = Trigger::Charging;
+            *timer = 0.0;
+        }
+        Trigger::Charging if *timer >= settings.charge => {
+            actions.fire = true;
+            *trigger = Trigger::Idle;
+            *timer = 0.0;
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,88 @@
+use crate::difficulty::{Difficulty, DifficultySettings};
+use crate::headless::Headless;
+use crate::replay::Playback;
+use crate::score::{RunStats, Score};
+use crate::user_data;
+use crate::GameState;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = "highscores.toml";
+/// Scores kept for each difficulty
+const KEEP: usize = 10;
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        let high_scores: HighScores = user_data::load(&HighScores::path(), "high scores");
+
+        app.insert_resource(high_scores).add_systems(
+            OnEnter(GameState::GameOver),
+            record
+                .run_if(not(resource_exists::<Headless>))
+                .run_if(not(resource_exists::<Playback>)),
+        );
+    }
+}
+
+/// Best runs, each tagged with the difficulty it was played on
+#[derive(Resource, serde::Serialize, serde::Deserialize, Default)]
+pub struct HighScores {
+    #[serde(default, rename = "score")]
+    pub scores: Vec<HighScore>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct HighScore {
+    pub difficulty: Difficulty,
+    pub points: u32,
+    pub survived: f32,
+    pub best_chain: u32,
+}
+
+impl HighScores {
+    /// In the user's data directory, see [`user_data::dir`]
+    pub fn path() -> PathBuf {
+        user_data::path(FILE_NAME)
+    }
+
+    /// Only ever compared against scores from the same difficulty
+    pub fn best(&self, difficulty: Difficulty) -> Option<&HighScore> {
+        self.scores
+            .iter()
+            .filter(|score| score.difficulty == difficulty)
+            .max_by_key(|score| score.points)
+    }
+
+    fn add(&mut self, high_score: HighScore) {
+        let difficulty = high_score.difficulty;
+        self.scores.push(high_score);
+        self.scores.sort_by(|a, b| b.points.cmp(&a.points));
+
+        let mut kept = 0;
+        self.scores.retain(|score| {
+            if score.difficulty != difficulty {
+                return true;
+            }
+            kept += 1;
+            kept <= KEEP
+        });
+    }
+}
+
+fn record(
+    mut high_scores: ResMut<HighScores>,
+    difficulty: Res<DifficultySettings>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+) {
+    high_scores.add(HighScore {
+        difficulty: difficulty.difficulty,
+        points: score.points,
+        survived: stats.survived,
+        best_chain: score.best_chain,
+    });
+
+    user_data::save(&HighScores::path(), &*high_scores, "high scores");
+}
@@ -0,0 +1,251 @@
+use crate::events::Threat;
+use crate::loading::FontAssets;
+use crate::player::{Arsenal, ChargeMeter, ChargeZone, Health, Player, SelectedWeapon};
+use crate::replay::Playback;
+use crate::score::{Combo, Score};
+use crate::GameState;
+use bevy::prelude::*;
+
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct ComboText;
+
+#[derive(Component)]
+struct HealthText;
+
+#[derive(Component)]
+struct ThreatText;
+
+#[derive(Component)]
+struct WeaponText;
+
+/// Shown while a shot is charging
+#[derive(Component)]
+struct ChargeBar;
+
+#[derive(Component)]
+struct ChargeFill;
+
+#[derive(Component)]
+struct PausedText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    update_score,
+                    update_combo,
+                    update_health,
+                    update_threat,
+                    update_weapon,
+                    update_charge,
+                    update_paused.run_if(not(resource_exists::<Playback>)),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_hud);
+    }
+}
+
+fn spawn_hud(mut commands: Commands, font_assets: Res<FontAssets>) {
+    let style = TextStyle {
+        font: font_assets.fantasque_sans.clone(),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            HudRoot,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(1.),
+                    top: Val::Percent(1.),
+                    flex_direction: FlexDirection::Column,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", style.clone()), HealthText));
+            parent.spawn((TextBundle::from_section("0", style.clone()), ScoreText));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Threat 1",
+                    TextStyle {
+                        color: Color::SALMON,
+                        ..style.clone()
+                    },
+                ),
+                ThreatText,
+            ));
+            parent.spawn((TextBundle::from_section("", style.clone()), WeaponText));
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(160.),
+                            height: Val::Px(12.),
+                            margin: UiRect::vertical(Val::Px(4.)),
+                            ..Default::default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        visibility: Visibility::Hidden,
+                        ..Default::default()
+                    },
+                    ChargeBar,
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..Default::default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..Default::default()
+                        },
+                        ChargeFill,
+                    ));
+                });
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: Color::ORANGE,
+                        ..style.clone()
+                    },
+                ),
+                ComboText,
+            ));
+            parent.spawn((
+                TextBundle {
+                    visibility: Visibility::Hidden,
+                    ..TextBundle::from_section("Paused", style)
+                },
+                PausedText,
+            ));
+        });
+}
+
+fn update_score(score: Res<Score>, mut query: Query<&mut Text, With<ScoreText>>) {
+    if !score.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = score.points.to_string();
+    }
+}
+
+fn update_combo(combo: Res<Combo>, mut query: Query<&mut Text, With<ComboText>>) {
+    if !combo.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if combo.chain > 0 {
+            format!("Chain x{}", combo.chain + 1)
+        } else {
+            String::new()
+        };
+    }
+}
+
+fn update_health(
+    player_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut query: Query<&mut Text, With<HealthText>>,
+) {
+    for health in player_query.iter() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = format!("HP {}/{}", health.current.max(0), health.max);
+        }
+    }
+}
+
+/// Whole levels only, counting from 1 for the run up to the end of the table
+fn update_threat(threat: Res<Threat>, mut query: Query<&mut Text, With<ThreatText>>) {
+    if !threat.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Threat {}", threat.level as u32 + 1);
+    }
+}
+
+/// Slot number as pressed on the keyboard, so counting from 1, in the weapon's colour
+fn update_weapon(
+    arsenal: Res<Arsenal>,
+    selected: Res<SelectedWeapon>,
+    mut query: Query<&mut Text, With<WeaponText>>,
+) {
+    if !arsenal.is_changed() && !selected.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        let weapon = arsenal.get(selected.slot);
+        text.sections[0].value = format!("{} {}", selected.slot + 1, weapon.name);
+        text.sections[0].style.color = weapon.color();
+    }
+}
+
+/// Filled to the charge level, gold while a release gets the perfect bonus and
+/// red once it's about to fizzle
+fn update_charge(
+    meter: Res<ChargeMeter>,
+    mut bar_query: Query<&mut Visibility, With<ChargeBar>>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor), With<ChargeFill>>,
+) {
+    if !meter.is_changed() {
+        return;
+    }
+
+    for mut visibility in bar_query.iter_mut() {
+        *visibility = if meter.charging {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    for (mut style, mut color) in fill_query.iter_mut() {
+        style.width = Val::Percent(meter.level * 100.);
+        *color = match meter.zone {
+            ChargeZone::Filling => Color::WHITE,
+            ChargeZone::Full => Color::GREEN,
+            ChargeZone::Perfect => Color::GOLD,
+            ChargeZone::Overcharged => Color::RED,
+        }
+        .into();
+    }
+}
+
+/// Replays have their own overlay, and pause the clock between frames anyway
+fn update_paused(time: Res<Time<Virtual>>, mut query: Query<&mut Visibility, With<PausedText>>) {
+    for mut visibility in query.iter_mut() {
+        visibility.set_if_neq(if time.is_paused() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+fn despawn_hud(mut commands: Commands, query: Query<Entity, With<HudRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,133 @@
+use crate::enemies::telegraph::{EnemySpawned, SpawnTelegraphed};
+use crate::main_camera::MainCamera;
+use crate::GameState;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// How far in from the edge of the screen indicators sit
+const MARGIN: f32 = 0.75;
+const WARNING_COLOR: Color = Color::ORANGE;
+const OFF_SCREEN_COLOR: Color = Color::RED;
+
+pub struct IndicatorsPlugin;
+
+impl Plugin for IndicatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Warnings>()
+            .add_systems(OnEnter(GameState::Playing), clear_warnings)
+            .add_systems(
+                Update,
+                (
+                    (add_warnings, draw_warnings).chain(),
+                    (track_arrivals, draw_off_screen).chain(),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+struct Warning {
+    position: Vec2,
+    size: Vec2,
+    timer: Timer,
+}
+
+/// Spawns that have been telegraphed but haven't appeared yet
+#[derive(Resource, Default)]
+struct Warnings(Vec<Warning>);
+
+/// Pointed at from the edge of the screen for as long as it's off it
+#[derive(Component)]
+struct OffScreenIndicator {
+    size: Vec2,
+}
+
+/// The part of the world the camera shows, shrunk by `MARGIN`
+fn view(camera: &Query<(&OrthographicProjection, &GlobalTransform), With<MainCamera>>) -> Rect {
+    let (projection, transform) = camera.single();
+    let centre = transform.translation().truncate();
+    Rect {
+        min: projection.area.min + centre,
+        max: projection.area.max + centre,
+    }
+    .inflate(-MARGIN)
+}
+
+/// An arrow at the edge of `view` pointing out at `target`, longer for bigger
+/// enemies. Nothing when `target` is on screen
+fn edge_arrow(gizmos: &mut Gizmos, view: Rect, target: Vec2, size: Vec2, color: Color) {
+    let edge = target.clamp(view.min, view.max);
+    let direction = (target - edge).normalize_or_zero();
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    let length = 0.5 + 0.3 * size.max_element();
+    gizmos.arrow_2d(edge - direction * length, edge, color);
+}
+
+fn clear_warnings(mut warnings: ResMut<Warnings>) {
+    warnings.0.clear();
+}
+
+fn add_warnings(mut warnings: ResMut<Warnings>, mut telegraphed: EventReader<SpawnTelegraphed>) {
+    for event in telegraphed.read() {
+        warnings.0.push(Warning {
+            position: event.position,
+            size: event.size,
+            timer: Timer::from_seconds(event.delay, TimerMode::Once),
+        });
+    }
+}
+
+/// Pulses at the spawn, or at the edge of the screen when it's off it, faster
+/// as the enemy gets closer to appearing
+fn draw_warnings(
+    time: Res<Time>,
+    mut warnings: ResMut<Warnings>,
+    camera: Query<(&OrthographicProjection, &GlobalTransform), With<MainCamera>>,
+    mut gizmos: Gizmos,
+) {
+    let view = view(&camera);
+
+    warnings.0.retain_mut(|warning| {
+        warning.timer.tick(time.delta());
+        !warning.timer.finished()
+    });
+
+    for warning in warnings.0.iter() {
+        let elapsed = warning.timer.elapsed_secs();
+        let pulse = 1.0 + 0.25 * (elapsed * elapsed * 4.0 * TAU).sin();
+        let radius = 0.5 * warning.size.max_element() * pulse;
+        let color = WARNING_COLOR.with_a(0.4 + 0.6 * warning.timer.fraction());
+
+        gizmos.circle_2d(warning.position.clamp(view.min, view.max), radius, color);
+        edge_arrow(&mut gizmos, view, warning.position, warning.size, color);
+    }
+}
+
+fn track_arrivals(mut commands: Commands, mut spawned: EventReader<EnemySpawned>) {
+    for event in spawned.read() {
+        if let Some(mut entity) = commands.get_entity(event.entity) {
+            entity.insert(OffScreenIndicator { size: event.size });
+        }
+    }
+}
+
+fn draw_off_screen(
+    query: Query<(&Transform, &OffScreenIndicator)>,
+    camera: Query<(&OrthographicProjection, &GlobalTransform), With<MainCamera>>,
+    mut gizmos: Gizmos,
+) {
+    let view = view(&camera);
+
+    for (transform, indicator) in query.iter() {
+        edge_arrow(
+            &mut gizmos,
+            view,
+            transform.translation.truncate(),
+            indicator.size,
+            OFF_SCREEN_COLOR,
+        );
+    }
+}
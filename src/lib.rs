@@ -1,26 +1,54 @@
+pub mod actions;
 #[cfg(debug_assertions)]
 mod debug;
+pub mod difficulty;
 mod enemies;
+mod gameover;
 pub mod events;
+pub mod headless;
+pub mod highscores;
+mod hud;
+mod indicators;
 mod loading;
 mod main_camera;
 mod menu;
 mod player;
+pub mod preferences;
+pub mod replay;
+pub mod rng;
+pub mod score;
+pub mod user_data;
+pub mod waves;
 mod world;
 
+use crate::actions::ActionsPlugin;
+use crate::difficulty::DifficultyPlugin;
 use crate::enemies::EnemiesPlugin;
 use crate::events::EventsPlugin;
+use crate::gameover::GameOverPlugin;
+use crate::headless::HeadlessPlugin;
+use crate::highscores::HighScoresPlugin;
+use crate::hud::HudPlugin;
+use crate::indicators::IndicatorsPlugin;
 use crate::loading::LoadingPlugin;
 use crate::main_camera::MainCameraPlugin;
 use crate::menu::MenuPlugin;
 use crate::player::PlayerPlugin;
+use crate::preferences::PreferencesPlugin;
+use crate::replay::ReplayPlugin;
+use crate::rng::RngPlugin;
+use crate::score::ScorePlugin;
+use crate::waves::WavesPlugin;
 use crate::world::WorldPlugin;
 
 use bevy::app::App;
+use bevy::hierarchy::HierarchyPlugin;
 use bevy::prelude::*;
+use bevy::transform::TransformPlugin;
 use bevy_xpbd_2d::prelude::*;
 #[cfg(debug_assertions)]
 use debug::DebugPlugin;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States, SystemSet)]
 enum GameState {
@@ -28,28 +56,76 @@ enum GameState {
     Loading,
     Menu,
     Playing,
+    GameOver,
 }
 
-pub struct GamePlugin;
+/// Run condition for gameplay systems: a run is in progress and the clock isn't
+/// paused. Systems gated on this are skipped outright while paused, so nothing
+/// that counts frames rather than time can drift
+pub(crate) fn running(state: Res<State<GameState>>, time: Res<Time<Virtual>>) -> bool {
+    *state.get() == GameState::Playing && !time.is_paused()
+}
+
+/// The whole game. With `headless` set it runs on top of `MinimalPlugins`: no
+/// window, camera, menu or textures, and the player is driven by
+/// [`headless::SimulatedInput`]
+#[derive(Default)]
+pub struct GamePlugin {
+    pub headless: bool,
+    /// Headless only: every update advances the game by exactly this much
+    /// instead of the wall-clock time since the last one, so a loop of
+    /// `App::update` runs as fast as the machine allows. At most a quarter of a
+    /// second, the virtual clock caps each step there
+    pub step: Option<Duration>,
+}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        let headless = self.headless;
+
+        if headless {
+            if !app.is_plugin_added::<AssetPlugin>() {
+                app.add_plugins(AssetPlugin::default());
+            }
+            if !app.is_plugin_added::<TransformPlugin>() {
+                app.add_plugins(TransformPlugin);
+            }
+            if !app.is_plugin_added::<HierarchyPlugin>() {
+                app.add_plugins(HierarchyPlugin);
+            }
+            app.add_plugins(HeadlessPlugin { step: self.step });
+        }
+
         app.init_state::<GameState>()
-            .add_plugins(LoadingPlugin)
+            .add_plugins(ActionsPlugin { headless })
+            .add_plugins(ReplayPlugin { headless })
+            .add_plugins(RngPlugin)
+            .add_plugins(LoadingPlugin { headless })
+            .add_plugins(DifficultyPlugin)
             .add_plugins(EventsPlugin)
-            .add_plugins(MainCameraPlugin)
-            .add_plugins(MenuPlugin)
+            .add_plugins(WavesPlugin)
             .add_plugins(WorldPlugin)
-            .add_plugins(PlayerPlugin)
+            .add_plugins(PlayerPlugin { headless })
             .add_plugins(EnemiesPlugin)
+            .add_plugins(ScorePlugin)
+            .add_plugins(HighScoresPlugin)
+            .add_plugins(GameOverPlugin { headless })
             .add_plugins(PhysicsPlugins::default())
             // .add_systems(Update, cleanup_far_entities.run_if(in_state(GameState::Playing)))
             // .insert_resource(Gravity::from(Vec2::new(0.0, -9.81)));
             ;
 
-        #[cfg(debug_assertions)]
-        {
-            app.add_plugins(DebugPlugin);
+        if !headless {
+            app.add_plugins(PreferencesPlugin)
+                .add_plugins(MainCameraPlugin)
+                .add_plugins(MenuPlugin)
+                .add_plugins(HudPlugin)
+                .add_plugins(IndicatorsPlugin);
+
+            #[cfg(debug_assertions)]
+            {
+                app.add_plugins(DebugPlugin);
+            }
         }
     }
 }
@@ -66,7 +142,7 @@ impl Plugin for GamePlugin {
 //     }
 // }
 
-#[derive(PhysicsLayer)]
+#[derive(PhysicsLayer, serde::Deserialize, Clone, Copy)]
 pub(crate) enum PhysicsLayers {
     Ground,
     CliffEdge,
@@ -1,26 +1,138 @@
-use crate::{events::TimeTable, GameState};
-use bevy::prelude::*;
+use crate::{
+    difficulty::DifficultyPresets,
+    enemies::{archetype::EnemyArchetypes, spawn_point::SpawnPointTable},
+    events::TimeTable,
+    player::{Arsenal, PlayerSettings},
+    waves::WaveScript,
+    GameState,
+};
+use bevy::{asset::LoadState, prelude::*, utils::HashMap};
 use bevy_asset_loader::prelude::*;
 use bevy_common_assets::toml::TomlAssetPlugin;
 // use bevy_kira_audio::AudioSource;
 
-pub struct LoadingPlugin;
+/// Every file in `GameData`, checked while loading so a broken one is reported
+/// instead of the game waiting on it forever
+const DATA_FILES: [&str; 10] = [
+    "spawn-rates.easy.time.toml",
+    "spawn-rates.time.toml",
+    "spawn-rates.hard.time.toml",
+    "spawn-rates.nightmare.time.toml",
+    "difficulty.presets.toml",
+    "archetypes.enemies.toml",
+    "spawn.points.toml",
+    "settings.player.toml",
+    "arsenal.weapons.toml",
+    "script.waves.toml",
+];
+
+pub struct LoadingPlugin {
+    /// Only load gameplay data, fonts and textures are left as default handles
+    pub headless: bool,
+}
 
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(TomlAssetPlugin::<TimeTable>::new(&["time.toml"]))
-            .add_loading_state(
-                LoadingState::new(GameState::Loading)
-                    .continue_to_state(GameState::Menu)
-                    .load_collection::<FontAssets>()
-                    .load_collection::<TextureAssets>()
-                    .load_collection::<GameData>(), // .load_collection::<AudioAssets>()
-                                                    // .build(app)
+            .add_plugins(TomlAssetPlugin::<EnemyArchetypes>::new(&["enemies.toml"]))
+            .add_plugins(TomlAssetPlugin::<SpawnPointTable>::new(&["points.toml"]))
+            .add_plugins(TomlAssetPlugin::<PlayerSettings>::new(&["player.toml"]))
+            .add_plugins(TomlAssetPlugin::<Arsenal>::new(&["weapons.toml"]))
+            .add_plugins(TomlAssetPlugin::<WaveScript>::new(&["waves.toml"]))
+            .add_plugins(TomlAssetPlugin::<DifficultyPresets>::new(&["presets.toml"]))
+            .add_systems(
+                Update,
+                report_failed_loads.run_if(in_state(GameState::Loading)),
             );
+
+        if self.headless {
+            app.init_resource::<FontAssets>()
+                .init_resource::<TextureAssets>()
+                .add_loading_state(
+                    LoadingState::new(GameState::Loading)
+                        .continue_to_state(GameState::Menu)
+                        .load_collection::<GameData>(),
+                );
+        } else {
+            app.add_systems(OnEnter(GameState::Loading), spawn_loading_screen)
+                .add_systems(OnExit(GameState::Loading), despawn_loading_screen)
+                .add_loading_state(
+                    LoadingState::new(GameState::Loading)
+                        .continue_to_state(GameState::Menu)
+                        .load_collection::<FontAssets>()
+                        .load_collection::<TextureAssets>()
+                        .load_collection::<GameData>(), // .load_collection::<AudioAssets>()
+                                                        // .build(app)
+                );
+        }
     }
 }
 
-#[derive(Resource, AssetCollection)]
+#[derive(Component)]
+struct LoadingText;
+
+/// Uses Bevy's built in font, the game's own is still loading
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Loading...",
+            TextStyle {
+                font_size: 28.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(1.),
+            top: Val::Percent(1.),
+            ..default()
+        }),
+        LoadingText,
+    ));
+}
+
+fn despawn_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingText>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// A file that fails to load, e.g. with a TOML type error, holds the loading
+/// state back for good. Names it in the log, where Bevy has already said why,
+/// and on the loading screen. With hot reload on, fixing the file carries on
+fn report_failed_loads(
+    asset_server: Res<AssetServer>,
+    mut reported: Local<Vec<&'static str>>,
+    mut query: Query<&mut Text, With<LoadingText>>,
+) {
+    let failed: Vec<&'static str> = DATA_FILES
+        .into_iter()
+        .filter(|path| {
+            asset_server
+                .get_handle_untyped(*path)
+                .and_then(|handle| asset_server.get_load_state(handle.id()))
+                == Some(LoadState::Failed)
+        })
+        .collect();
+    if failed == *reported {
+        return;
+    }
+
+    for path in failed.iter().filter(|path| !reported.contains(path)) {
+        error!("Couldn't load {path}, the game can't start until it's fixed");
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if failed.is_empty() {
+            "Loading...".into()
+        } else {
+            format!("Couldn't load:\n{}\nSee the log for why", failed.join("\n"))
+        };
+    }
+    *reported = failed;
+}
+
+#[derive(Resource, AssetCollection, Default)]
 pub struct FontAssets {
     #[asset(path = "fonts/FantasqueSansMono-Bold.ttf")]
     pub fantasque_sans: Handle<Font>,
@@ -32,7 +144,7 @@ pub struct FontAssets {
 //     pub flying: Handle<AudioSource>,
 // }
 
-#[derive(Resource, AssetCollection)]
+#[derive(Resource, AssetCollection, Default)]
 pub struct TextureAssets {
     #[asset(path = "textures/ground.png")]
     pub ground: Handle<Image>,
@@ -52,6 +164,27 @@ pub struct TextureAssets {
 
 #[derive(Resource, AssetCollection)]
 pub struct GameData {
-    #[asset(path = "spawn-rates.time.toml")]
-    pub spawn_rates: Handle<TimeTable>,
+    /// Keyed by path, a difficulty preset picks one by name
+    #[asset(
+        paths(
+            "spawn-rates.easy.time.toml",
+            "spawn-rates.time.toml",
+            "spawn-rates.hard.time.toml",
+            "spawn-rates.nightmare.time.toml"
+        ),
+        collection(typed, mapped)
+    )]
+    pub spawn_tables: HashMap<String, Handle<TimeTable>>,
+    #[asset(path = "difficulty.presets.toml")]
+    pub difficulty_presets: Handle<DifficultyPresets>,
+    #[asset(path = "archetypes.enemies.toml")]
+    pub enemy_archetypes: Handle<EnemyArchetypes>,
+    #[asset(path = "spawn.points.toml")]
+    pub spawn_points: Handle<SpawnPointTable>,
+    #[asset(path = "settings.player.toml")]
+    pub player_settings: Handle<PlayerSettings>,
+    #[asset(path = "arsenal.weapons.toml")]
+    pub arsenal: Handle<Arsenal>,
+    #[asset(path = "script.waves.toml")]
+    pub waves: Handle<WaveScript>,
 }
@@ -16,7 +16,7 @@ fn main() {
             ..default()
         }))
         // .add_plugins(EditorPlugin)
-        .add_plugins(GamePlugin)
+        .add_plugins(GamePlugin::default())
         // .add_startup_system(spawn_window)
         .run();
 }
@@ -1,13 +1,25 @@
 use crate::{
-    enemies::{Explosion, Giant, Hop},
+    enemies::{spawn_point::SpawnPoints, Behemoth, Explosion, Giant, Hop},
+    rng::GameRng,
     GameState,
 };
-use bevy::{prelude::*, render::camera::*};
+use bevy::{prelude::*, render::camera::*, utils::HashSet};
 use bevy_framepace::{FramepacePlugin, FramepaceSettings, Limiter};
 use rand::Rng;
 
 const CAM_POS: Vec3 = Vec3::new(0.0, 8.0, -1.0);
 
+// Footsteps: a Giant adds trauma on every frame it's on the ground, topping out
+// at GIANT_TRAUMA for as long as it stands there. A Behemoth goes further,
+// holding trauma at MAX_TRAUMA while it stands and for BEHEMOTH_LANDING seconds
+// after each landing, so its shake peaks higher and lasts longer
+/// Trauma decays by this much a second
+const TRAUMA_DECAY: f32 = 3.0;
+const MAX_TRAUMA: f32 = 1.6;
+const GIANT_TRAUMA: f32 = 1.333;
+const GIANT_STEP: f32 = 0.5;
+const BEHEMOTH_LANDING: f32 = 0.5;
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -23,9 +35,11 @@ impl Plugin for MainCameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(FramepacePlugin)
             .add_systems(Startup, setup_camera)
+            .add_systems(Update, track_visible_width)
             .add_systems(
                 Update,
-                (camera_shake, explosion_trauma, giant_steps).run_if(in_state(GameState::Playing)),
+                (camera_shake, explosion_trauma, giant_steps, behemoth_steps)
+                    .run_if(in_state(GameState::Playing)),
             );
     }
 }
@@ -49,6 +63,16 @@ fn setup_camera(mut commands: Commands, mut framepace_settings: ResMut<Framepace
     ));
 }
 
+/// Off-screen spawns start at the edge of what the camera shows
+fn track_visible_width(
+    query: Query<&OrthographicProjection, (With<MainCamera>, Changed<OrthographicProjection>)>,
+    mut spawn_points: ResMut<SpawnPoints>,
+) {
+    for projection in query.iter() {
+        spawn_points.half_width = projection.area.width() / 2.0;
+    }
+}
+
 fn explosion_trauma(
     explosion_query: Query<&Explosion>,
     mut trauma_query: Query<&mut CameraTrauma>,
@@ -60,14 +84,39 @@ fn explosion_trauma(
 
 fn giant_steps(giant_query: Query<&Hop, With<Giant>>, mut trauma_query: Query<&mut CameraTrauma>) {
     for g in giant_query.iter() {
-        if g.grounded {
-            trauma_query.single_mut().trauma += 0.5_f32.clamp(0.0, 1.0);
+        let mut trauma = trauma_query.single_mut();
+        if g.grounded && trauma.trauma < GIANT_TRAUMA {
+            trauma.trauma = (trauma.trauma + GIANT_STEP).min(GIANT_TRAUMA);
+        }
+    }
+}
+
+fn behemoth_steps(
+    time: Res<Time>,
+    behemoth_query: Query<(Entity, &Hop), With<Behemoth>>,
+    mut trauma_query: Query<&mut CameraTrauma>,
+    mut grounded: Local<HashSet<Entity>>,
+    mut landing: Local<f32>,
+) {
+    *landing -= time.delta_seconds();
+    grounded.retain(|entity| behemoth_query.contains(*entity));
+    for (entity, b) in behemoth_query.iter() {
+        if !b.grounded {
+            grounded.remove(&entity);
+        } else if grounded.insert(entity) {
+            *landing = BEHEMOTH_LANDING;
         }
     }
+
+    if *landing > 0.0 || !grounded.is_empty() {
+        let mut trauma = trauma_query.single_mut();
+        trauma.trauma = trauma.trauma.max(MAX_TRAUMA);
+    }
 }
 
 fn camera_shake(
     time: Res<Time>,
+    mut game_rng: ResMut<GameRng>,
     mut trauma_query: Query<&mut CameraTrauma>,
     mut trans_query: Query<&mut Transform, With<MainCamera>>,
 ) {
@@ -81,12 +130,13 @@ fn camera_shake(
         return;
     }
 
-    trauma.trauma = trauma.trauma.clamp(0.0, 1.333);
+    trauma.trauma = trauma.trauma.clamp(0.0, MAX_TRAUMA);
     let shake = trauma.trauma.powf(3.0);
 
-    trans.translation.x += 0.1 * shake * rand::thread_rng().gen_range(-1.0..1.0);
-    trans.translation.y += 0.1 * shake * rand::thread_rng().gen_range(-1.0..1.0);
-    trans.rotate_z(0.005 * shake * rand::thread_rng().gen_range(-1.0..1.0));
+    let rng = &mut game_rng.cosmetic;
+    trans.translation.x += 0.1 * shake * rng.gen_range(-1.0..1.0);
+    trans.translation.y += 0.1 * shake * rng.gen_range(-1.0..1.0);
+    trans.rotate_z(0.005 * shake * rng.gen_range(-1.0..1.0));
 
-    trauma.trauma -= time.delta_seconds() * 3.0;
+    trauma.trauma -= time.delta_seconds() * TRAUMA_DECAY;
 }
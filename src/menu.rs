@@ -1,14 +1,52 @@
+use crate::actions::{Action, ActionSet, Actions, Binding, Bindings, DeviceButtons};
+use crate::difficulty::Difficulty;
+use crate::highscores::HighScores;
 use crate::loading::FontAssets;
+use crate::preferences::Preferences;
 use crate::GameState;
 use bevy::prelude::*;
 
+/// Shown on the controls page when nothing else is
+const HINT: &str = "Click an action to bind a key, mouse or gamepad button to it";
+
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
-            .add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .init_resource::<MenuPage>()
+            .add_systems(
+                OnEnter(GameState::Menu),
+                (
+                    setup_menu,
+                    setup_controls,
+                    (
+                        highlight_difficulty,
+                        update_best,
+                        update_bindings,
+                        update_preview,
+                        open_main_page,
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    click_play_button,
+                    click_difficulty_button,
+                    menu_actions
+                        .after(ActionSet::Observe)
+                        .run_if(resource_equals(MenuPage::Main)),
+                    (highlight_difficulty, update_best).run_if(resource_changed::<Difficulty>),
+                    (click_controls_button, capture_binding.run_if(capturing)).chain(),
+                    update_bindings.run_if(resource_changed::<Bindings>),
+                    click_preview_button,
+                    update_preview.run_if(resource_changed::<Preferences>),
+                    show_page.run_if(resource_changed::<MenuPage>),
+                )
+                    .run_if(in_state(GameState::Menu)),
+            )
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
@@ -17,6 +55,7 @@ impl Plugin for MenuPlugin {
 struct ButtonColors {
     normal: BackgroundColor,
     hovered: BackgroundColor,
+    selected: BackgroundColor,
 }
 
 impl Default for ButtonColors {
@@ -24,44 +63,301 @@ impl Default for ButtonColors {
         ButtonColors {
             normal: Color::rgb(0.15, 0.15, 0.15).into(),
             hovered: Color::rgb(0.25, 0.25, 0.25).into(),
+            selected: Color::rgb(0.35, 0.25, 0.1).into(),
         }
     }
 }
 
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+enum MenuPage {
+    #[default]
+    Main,
+    Controls,
+    /// Waiting for a new button for the action
+    Capturing(Action),
+}
+
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct MainPage;
+
+#[derive(Component)]
+struct ControlsPage;
+
+#[derive(Component, Clone, Copy)]
+enum ControlsButton {
+    Open,
+    Rebind(Action),
+    Reset,
+    Back,
+}
+
+#[derive(Component)]
+struct BindingText(Action);
+
+#[derive(Component)]
+struct ControlsMessage;
+
+#[derive(Component)]
+struct PlayButton;
+
+/// Turns the charging shot's arc on and off
+#[derive(Component)]
+struct PreviewButton;
+
+#[derive(Component)]
+struct PreviewText;
+
+#[derive(Component)]
+struct DifficultyButton(Difficulty);
+
+#[derive(Component)]
+struct BestText;
+
 fn setup_menu(
     mut commands: Commands,
     button_colors: Res<ButtonColors>,
     font_assets: Res<FontAssets>,
 ) {
+    let style = |font_size: f32| TextStyle {
+        font: font_assets.fantasque_sans.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
     commands
-        .spawn(ButtonBundle {
-            style: Style {
-                width: Val::Px(120.0),
-                height: Val::Px(50.0),
-                margin: UiRect::all(Val::Auto),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
+        .spawn((
+            MenuRoot,
+            MainPage,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            background_color: button_colors.normal,
-            ..Default::default()
-        })
+        ))
         .with_children(|parent| {
-            parent.spawn(TextBundle {
-                text: Text {
-                    sections: vec![TextSection {
-                        value: "Play".to_string(),
-                        style: TextStyle {
-                            font: font_assets.fantasque_sans.clone(),
-                            font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for difficulty in Difficulty::ALL {
+                        parent
+                            .spawn((
+                                DifficultyButton(difficulty),
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(150.0),
+                                        height: Val::Px(40.0),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..Default::default()
+                                    },
+                                    background_color: button_colors.normal,
+                                    ..Default::default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("{difficulty:?}"),
+                                    style(24.0),
+                                ));
+                            });
+                    }
+                });
+
+            parent.spawn((TextBundle::from_section("", style(24.0)), BestText));
+
+            parent
+                .spawn((
+                    PlayButton,
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(120.0),
+                            height: Val::Px(50.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: button_colors.normal,
+                        ..Default::default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle {
+                        text: Text {
+                            sections: vec![TextSection {
+                                value: "Play".to_string(),
+                                style: style(40.0),
+                            }],
+                            justify: JustifyText::Center,
+                            ..Default::default()
                         },
-                    }],
-                    justify: JustifyText::Center,
+                        ..Default::default()
+                    });
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    let button_style = |width: f32| Style {
+                        width: Val::Px(width),
+                        height: Val::Px(40.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    };
+
+                    parent
+                        .spawn((
+                            ControlsButton::Open,
+                            ButtonBundle {
+                                style: button_style(160.0),
+                                background_color: button_colors.normal,
+                                ..Default::default()
+                            },
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Controls", style(24.0)));
+                        });
+
+                    parent
+                        .spawn((
+                            PreviewButton,
+                            ButtonBundle {
+                                style: button_style(240.0),
+                                background_color: button_colors.normal,
+                                ..Default::default()
+                            },
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((TextBundle::from_section("", style(24.0)), PreviewText));
+                        });
+                });
+        });
+}
+
+/// Hidden until the Controls button is clicked
+fn setup_controls(
+    mut commands: Commands,
+    button_colors: Res<ButtonColors>,
+    font_assets: Res<FontAssets>,
+) {
+    let style = |font_size: f32| TextStyle {
+        font: font_assets.fantasque_sans.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let button = |button, width: f32| {
+        (
+            button,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(width),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal,
+                ..Default::default()
+            },
+        )
+    };
+
+    commands
+        .spawn((
+            MenuRoot,
+            ControlsPage,
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
                     ..Default::default()
                 },
                 ..Default::default()
-            });
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Controls", style(40.0)));
+
+            for action in Action::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(16.0),
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(action.label(), style(24.0)).with_style(
+                                Style {
+                                    width: Val::Px(220.0),
+                                    ..Default::default()
+                                },
+                            ),
+                        );
+                        parent
+                            .spawn(button(ControlsButton::Rebind(action), 480.0))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    TextBundle::from_section("", style(20.0)),
+                                    BindingText(action),
+                                ));
+                            });
+                    });
+            }
+
+            parent.spawn((TextBundle::from_section(HINT, style(20.0)), ControlsMessage));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(16.0),
+                        margin: UiRect::top(Val::Px(16.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (controls_button, label) in [
+                        (ControlsButton::Reset, "Reset to defaults"),
+                        (ControlsButton::Back, "Back"),
+                    ] {
+                        parent
+                            .spawn(button(controls_button, 240.0))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(label, style(24.0)));
+                            });
+                    }
+                });
         });
 }
 
@@ -70,7 +366,7 @@ fn click_play_button(
     mut state: ResMut<NextState<GameState>>,
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<PlayButton>),
     >,
 ) {
     interaction_query
@@ -88,6 +384,246 @@ fn click_play_button(
         });
 }
 
-fn cleanup_menu(mut commands: Commands, button: Query<Entity, With<Button>>) {
-    commands.entity(button.single()).despawn_recursive();
+fn click_difficulty_button(
+    button_colors: Res<ButtonColors>,
+    mut difficulty: ResMut<Difficulty>,
+    mut interaction_query: Query<
+        (&Interaction, &DifficultyButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => *difficulty = button.0,
+            _ if button.0 == *difficulty => *color = button_colors.selected,
+            Interaction::Hovered => *color = button_colors.hovered,
+            Interaction::None => *color = button_colors.normal,
+        }
+    }
+}
+
+/// Fire starts the run and switching weapons steps through the difficulties,
+/// for playing without the mouse
+fn menu_actions(
+    mut actions: ResMut<Actions>,
+    mut difficulty: ResMut<Difficulty>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    if let Some(switch) = actions.switch_weapon.take() {
+        let current = Difficulty::ALL
+            .iter()
+            .position(|d| *d == *difficulty)
+            .unwrap_or_default();
+        if let Some(next) = Difficulty::ALL.get(switch.resolve(current, Difficulty::ALL.len())) {
+            difficulty.set_if_neq(*next);
+        }
+    }
+
+    if std::mem::take(&mut actions.fire) {
+        state.set(GameState::Playing);
+    }
+}
+
+/// Saved straight away, like the controls
+fn click_preview_button(
+    button_colors: Res<ButtonColors>,
+    mut preferences: ResMut<Preferences>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<PreviewButton>),
+    >,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                preferences.trajectory_preview = !preferences.trajectory_preview;
+                preferences.store();
+            }
+            Interaction::Hovered => *color = button_colors.hovered,
+            Interaction::None => *color = button_colors.normal,
+        }
+    }
+}
+
+fn update_preview(preferences: Res<Preferences>, mut query: Query<&mut Text, With<PreviewText>>) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if preferences.trajectory_preview {
+            "Arc preview: On".to_string()
+        } else {
+            "Arc preview: Off".to_string()
+        };
+    }
+}
+
+fn highlight_difficulty(
+    button_colors: Res<ButtonColors>,
+    difficulty: Res<Difficulty>,
+    mut query: Query<(&DifficultyButton, &mut BackgroundColor)>,
+) {
+    for (button, mut color) in query.iter_mut() {
+        *color = if button.0 == *difficulty {
+            button_colors.selected
+        } else {
+            button_colors.normal
+        };
+    }
+}
+
+fn update_best(
+    difficulty: Res<Difficulty>,
+    high_scores: Res<HighScores>,
+    mut query: Query<&mut Text, With<BestText>>,
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = match high_scores.best(*difficulty) {
+            Some(best) => format!("Best on {:?}: {}", *difficulty, best.points),
+            None => format!("No runs on {:?} yet", *difficulty),
+        };
+    }
+}
+
+fn open_main_page(mut page: ResMut<MenuPage>) {
+    page.set_if_neq(MenuPage::Main);
+}
+
+fn capturing(page: Res<MenuPage>) -> bool {
+    matches!(*page, MenuPage::Capturing(_))
+}
+
+fn show_page(
+    page: Res<MenuPage>,
+    mut main: Query<&mut Style, (With<MainPage>, Without<ControlsPage>)>,
+    mut controls: Query<&mut Style, (With<ControlsPage>, Without<MainPage>)>,
+) {
+    let (main_display, controls_display) = match *page {
+        MenuPage::Main => (Display::Flex, Display::None),
+        MenuPage::Controls | MenuPage::Capturing(_) => (Display::None, Display::Flex),
+    };
+    for mut style in main.iter_mut() {
+        style.display = main_display;
+    }
+    for mut style in controls.iter_mut() {
+        style.display = controls_display;
+    }
+}
+
+/// Buttons are ignored while a binding is being captured, so clicking to bind
+/// a mouse button doesn't also press whatever is under the cursor
+fn click_controls_button(
+    button_colors: Res<ButtonColors>,
+    mut page: ResMut<MenuPage>,
+    mut bindings: ResMut<Bindings>,
+    mut actions: ResMut<Actions>,
+    mut message: Query<&mut Text, With<ControlsMessage>>,
+    mut interaction_query: Query<
+        (&Interaction, &ControlsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    let mut say = |text: String| {
+        for mut message in message.iter_mut() {
+            message.sections[0].value = text.clone();
+        }
+    };
+
+    let was_capturing = matches!(*page, MenuPage::Capturing(_));
+    for (interaction, button, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed if was_capturing => {}
+            Interaction::Pressed => match *button {
+                ControlsButton::Open => *page = MenuPage::Controls,
+                ControlsButton::Rebind(action) => {
+                    *page = MenuPage::Capturing(action);
+                    *color = button_colors.selected;
+                    say(format!(
+                        "Press a key or button for {}, Escape cancels",
+                        action.label()
+                    ));
+                }
+                ControlsButton::Reset => {
+                    *bindings = Bindings::default();
+                    bindings.store();
+                    say(HINT.to_string());
+                }
+                ControlsButton::Back => {
+                    // Nothing pressed while rebinding should act on the menu
+                    actions.clear();
+                    *page = MenuPage::Main;
+                }
+            },
+            Interaction::Hovered => *color = button_colors.hovered,
+            Interaction::None => *color = button_colors.normal,
+        }
+    }
+}
+
+/// Takes the next button pressed on any device. Refuses ones already bound to
+/// another action, or kept for picking weapon slots
+fn capture_binding(
+    button_colors: Res<ButtonColors>,
+    devices: DeviceButtons,
+    mut page: ResMut<MenuPage>,
+    mut bindings: ResMut<Bindings>,
+    mut message: Query<&mut Text, With<ControlsMessage>>,
+    mut buttons: Query<(&ControlsButton, &mut BackgroundColor)>,
+) {
+    let MenuPage::Capturing(action) = *page else {
+        return;
+    };
+    // The click that started capturing is still down this frame
+    if page.is_changed() {
+        return;
+    }
+    let Some(binding) = Binding::first_pressed(&devices) else {
+        return;
+    };
+
+    let text = if binding == Binding::Key(KeyCode::Escape) {
+        *page = MenuPage::Controls;
+        HINT.to_string()
+    } else if binding.reserved() {
+        format!("{binding} picks a weapon slot, try another")
+    } else if let Some(other) = bindings.conflict(action, binding) {
+        format!(
+            "{binding} is already bound to {}, try another",
+            other.label()
+        )
+    } else {
+        bindings.rebind(action, binding);
+        bindings.store();
+        *page = MenuPage::Controls;
+        HINT.to_string()
+    };
+
+    for mut message in message.iter_mut() {
+        message.sections[0].value = text.clone();
+    }
+    if *page == MenuPage::Controls {
+        for (button, mut color) in buttons.iter_mut() {
+            if matches!(button, ControlsButton::Rebind(a) if *a == action) {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn update_bindings(bindings: Res<Bindings>, mut query: Query<(&mut Text, &BindingText)>) {
+    for (mut text, binding_text) in query.iter_mut() {
+        let bound = bindings.get(binding_text.0);
+        text.sections[0].value = if bound.is_empty() {
+            "Unbound".to_string()
+        } else {
+            bound
+                .iter()
+                .map(Binding::to_string)
+                .collect::<Vec<_>>()
+                .join(" / ")
+        };
+    }
+}
+
+fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
@@ -1,50 +1,111 @@
+use crate::actions::{ActionSet, Actions};
 use crate::enemies::enemy_projectile::EnemyProjectile;
-use crate::enemies::Enemy;
-use crate::main_camera::MainCamera;
+use crate::enemies::{spawn_explosion, ContactDamage, ContactResponse, Enemy};
+use crate::loading::GameData;
+use crate::score::RunStats;
+use crate::world::{ArenaEntity, Ground};
 use crate::PhysicsLayers;
-use crate::{loading::TextureAssets, DynamicActorBundle, GameState};
-use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use crate::{loading::TextureAssets, running, GameState};
+use bevy::{prelude::*, reflect::TypePath, time::Stopwatch, utils::HashSet};
 use bevy_xpbd_2d::prelude::*;
 
+mod charge;
+mod trajectory;
+mod weapons;
+
+pub(crate) use charge::{ChargeMeter, ChargeZone};
+use weapons::{spawn_shot, Behaviour, Fragment, Ricochets, Weapon};
+pub(crate) use weapons::{Arsenal, SelectedWeapon};
+
 pub const PLAYER_CENTER: Vec2 = Vec2::new(0.0, 8.75);
 pub const PLAYER_SIZE: Vec2 = Vec2::new(0.75, 1.5);
-
-pub struct PlayerPlugin;
+pub struct PlayerPlugin {
+    pub headless: bool,
+}
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(MouseData {
-            world_pos: Vec2::ZERO,
-            vec_from_player: Vec2::Y,
-        })
-        .add_systems(OnEnter(GameState::Playing), spawn_player)
-        .add_systems(
-            Update,
-            (
-                mouse_input,
-                aim,
-                launch,
-                projectile_destruction,
-                projectile_timeouts,
-                hit,
+        app.init_resource::<Arsenal>()
+            .init_resource::<PlayerSettings>()
+            .init_resource::<SelectedWeapon>()
+            .init_resource::<ChargeMeter>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (load_settings, spawn_player, weapons::reset_weapon).chain(),
             )
-                .run_if(in_state(GameState::Playing)),
-        );
+            .add_systems(
+                Update,
+                (
+                    (weapons::switch_weapon, charge_input)
+                        .chain()
+                        .after(ActionSet::Observe),
+                    aim.before(charge_input),
+                    launch,
+                    weapons::split_clusters,
+                    projectile_destruction,
+                    projectile_timeouts,
+                    hit,
+                    invulnerability,
+                )
+                    .run_if(running),
+            );
+
+        if !self.headless {
+            app.add_systems(
+                Update,
+                trajectory::draw_trajectory.after(aim).run_if(running),
+            );
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, TypePath, Asset, Resource)]
+pub struct PlayerSettings {
+    pub max_health: i32,
+    /// Seconds the player can't be hurt after a hit
+    pub invulnerability: f32,
+    /// Seconds between blinks while invulnerable
+    pub flicker: f32,
+}
+
+impl Default for PlayerSettings {
+    /// What `player.toml` ships with
+    fn default() -> Self {
+        Self {
+            max_health: 3,
+            invulnerability: 1.5,
+            flicker: 0.08,
+        }
     }
 }
 
 #[derive(Component)]
-struct Player;
+pub(crate) struct Player;
+
+#[derive(Component)]
+pub(crate) struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+/// Contacts are ignored until the timer runs out
+#[derive(Component)]
+struct Invulnerable {
+    timer: Timer,
+}
 
 #[derive(Component)]
 pub(crate) struct PlayerProjectile {
     pub size: i32,
+    /// Slot in the `Arsenal` it was fired from
+    pub weapon: usize,
+    /// Enemies it has already damaged, each is only hurt once per shot
+    pub hits: Vec<Entity>,
 }
 
 #[derive(Component)]
 struct Charging {
-    timer: Timer,
+    held: Stopwatch,
 }
 
 #[derive(Component)]
@@ -55,13 +116,28 @@ struct Timeout {
 #[derive(Component)]
 struct Fired;
 
-#[derive(Resource, Default)]
-struct MouseData {
-    world_pos: Vec2,
-    vec_from_player: Vec2,
+fn load_settings(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    settings_asset: Res<Assets<PlayerSettings>>,
+    arsenals: Res<Assets<Arsenal>>,
+) {
+    match settings_asset.get(&game_data.player_settings) {
+        Some(settings) => commands.insert_resource(settings.clone()),
+        None => warn!("player.toml didn't load, using the default player settings"),
+    }
+    if let Some(arsenal) = arsenals.get(&game_data.arsenal) {
+        if arsenal.len() > 0 {
+            let mut arsenal = arsenal.clone();
+            for error in arsenal.repair() {
+                error!("arsenal.weapons.toml: {error}");
+            }
+            commands.insert_resource(arsenal);
+        }
+    }
 }
 
-fn spawn_player(mut commands: Commands) {
+fn spawn_player(mut commands: Commands, settings: Res<PlayerSettings>) {
     commands
         .spawn(SpriteBundle {
             // texture: textures.texture_bevy.clone(),
@@ -74,6 +150,11 @@ fn spawn_player(mut commands: Commands) {
             ..Default::default()
         })
         .insert(Player)
+        .insert(Health {
+            current: settings.max_health,
+            max: settings.max_health,
+        })
+        .insert(ArenaEntity)
         .insert(RigidBody::Static)
         .insert(Collider::rectangle(PLAYER_SIZE.x, PLAYER_SIZE.y))
         .insert(CollidingEntities::default())
@@ -83,70 +164,51 @@ fn spawn_player(mut commands: Commands) {
         ));
 }
 
-fn spawn_projectile(commands: &mut Commands, texture_assets: Res<TextureAssets>) -> Entity {
-    let entity = &commands
-        .spawn(SpriteBundle {
-            texture: texture_assets.circle.clone(),
-            sprite: Sprite {
-                custom_size: Some(Vec2::ONE),
-                color: Color::GREEN,
-                ..Default::default()
-            },
-            transform: Transform::from_translation(Vec3::new(-10.0, 45.0, 0.0)),
-            ..Default::default()
-        })
-        .insert(PlayerProjectile { size: 1 })
-        .insert(DynamicActorBundle {
-            rigidbody: RigidBody::Static,
-            locked_axes: LockedAxes::ROTATION_LOCKED,
-            collider: Collider::circle(0.5),
-            collision_layers: CollisionLayers::new(
-                [PhysicsLayers::PlayerProj],
-                [
-                    PhysicsLayers::Ground,
-                    PhysicsLayers::Enemy,
-                    PhysicsLayers::EnemyProj,
-                ],
-            ),
-            mass: Mass(50.0),
-            friction: Friction::ZERO,
-            restitution: Restitution::PERFECTLY_ELASTIC,
-            ..Default::default()
-        })
-        .insert(Timeout {
-            timer: Timer::from_seconds(3.0, TimerMode::Once),
-        })
-        .insert(Charging {
-            timer: Timer::from_seconds(10.0, TimerMode::Repeating),
-        })
-        .insert(GravityScale(3.0))
-        .id();
-
-    *entity
-}
-
+/// Grows the held shot along its weapon's charge curve, dropping it if it's
+/// held past fizzling
 fn aim(
+    mut commands: Commands,
     time: Res<Time>,
-    mouse_data: Res<MouseData>,
-    mut proj_query: Query<(&mut PlayerProjectile, &mut Transform, &mut Charging)>,
+    actions: Res<Actions>,
+    arsenal: Res<Arsenal>,
+    mut meter: ResMut<ChargeMeter>,
+    mut proj_query: Query<(Entity, &mut PlayerProjectile, &mut Transform, &mut Charging)>,
 ) {
-    for (mut proj, mut proj_trans, mut charge) in proj_query.iter_mut() {
-        charge.timer.tick(time.delta());
-        let c = (charge.timer.elapsed_secs().sin().powi(2) * 4.0) + 1.0;
-        proj.size = c.round() as i32;
+    let mut reading = ChargeMeter::default();
+    for (entity, mut proj, mut proj_trans, mut charge) in proj_query.iter_mut() {
+        let held = charge.held.tick(time.delta()).elapsed_secs();
+        let weapon = arsenal.get(proj.weapon);
+        if weapon.curve.fizzled(held) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        proj.size = weapon.curve.size(held, weapon.max_charge);
+        reading = ChargeMeter {
+            charging: true,
+            level: weapon.curve.level(held),
+            zone: weapon.curve.zone(held),
+        };
         proj_trans.scale = Vec3::ONE * (0.5 + (0.05 * proj.size as f32));
-        proj_trans.translation = (2.0 * mouse_data.vec_from_player + PLAYER_CENTER).extend(0.0);
+        proj_trans.translation = (2.0 * actions.direction() + PLAYER_CENTER).extend(0.0);
     }
+    meter.set_if_neq(reading);
+}
+
+/// A bigger charge throws harder, levelling off towards the top
+pub(crate) fn launch_velocity(direction: Vec2, size: i32) -> Vec2 {
+    direction * (((size as f32 - 1.0).atan() * 12.0) + 7.0)
 }
 
 fn launch(
     mut commands: Commands,
-    mouse_data: Res<MouseData>,
+    actions: Res<Actions>,
+    arsenal: Res<Arsenal>,
     mut query: Query<(Entity, &mut LinearVelocity, &PlayerProjectile), With<Fired>>,
 ) {
     for (entity, mut vel, projectile) in query.iter_mut() {
-        let velocity =
-            mouse_data.vec_from_player * (((projectile.size as f32 - 1.0).atan() * 12.0) + 7.0);
+        let velocity = launch_velocity(actions.direction(), projectile.size)
+            * arsenal.get(projectile.weapon).speed;
         vel.x = velocity.x;
         vel.y = velocity.y;
         commands
@@ -156,46 +218,53 @@ fn launch(
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn mouse_input(
-    primary_window_query: Query<&Window, With<PrimaryWindow>>,
-    input: Res<ButtonInput<MouseButton>>,
-    cam_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-    proj_query: Query<Entity, (With<PlayerProjectile>, With<Charging>)>,
+fn charge_input(
+    mut proj_query: Query<(Entity, &mut PlayerProjectile, &Charging)>,
     texture_assets: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
+    time: Res<Time>,
+    mut selected: ResMut<SelectedWeapon>,
     mut commands: Commands,
-    mut mouse_data: ResMut<MouseData>,
-    mut events: EventReader<CursorMoved>,
+    mut actions: ResMut<Actions>,
+    mut stats: ResMut<RunStats>,
 ) {
-    let (camera, camera_transform) = cam_query.single();
-
-    let Ok(window) = primary_window_query.get_single() else {
-        return;
-    };
-
-    for e in events.read() {
-        let window_size = Vec2::new(window.width(), window.height());
-        let real_pos = Vec2::new(e.position.x, window_size.y - e.position.y);
-        let ndc = (real_pos / window_size) * 2.0 - Vec2::ONE;
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-        mouse_data.world_pos = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
-    }
-
-    let a = Vec2::Y;
-    let b = (mouse_data.world_pos - PLAYER_CENTER).normalize();
-    let mut angle = ((a.x * b.x) + (a.y * b.y)).acos();
-    let cross = (a.x * b.y) - (a.y * b.x);
-
-    if cross < 0.0 {
-        angle *= -1.0;
-    }
-
-    mouse_data.vec_from_player = Vec2::from_angle(angle).rotate(Vec2::Y).normalize();
+    let slot = selected.slot;
+    let weapon = arsenal.get(slot);
+    let muzzle = (2.0 * actions.direction() + PLAYER_CENTER).extend(0.0);
 
-    if input.just_pressed(MouseButton::Left) {
-        spawn_projectile(&mut commands, texture_assets);
-    } else if input.just_released(MouseButton::Left) {
-        for p in proj_query.iter() {
+    if actions.charge {
+        actions.charge = false;
+        if weapon.charge {
+            spawn_shot(
+                &mut commands,
+                &texture_assets,
+                &arsenal,
+                slot,
+                1,
+                Vec3::new(-10.0, 45.0, 0.0),
+                RigidBody::Static,
+            )
+            .insert(Charging {
+                held: Stopwatch::new(),
+            });
+        } else {
+            fire(&mut commands, &texture_assets, &arsenal, slot, muzzle);
+            stats.shots_fired += 1;
+            selected.repeat = (weapon.repeat > 0.0)
+                .then(|| Timer::from_seconds(weapon.repeat, TimerMode::Repeating));
+        }
+    } else if actions.fire {
+        actions.fire = false;
+        selected.repeat = None;
+        for (p, mut proj, charge) in proj_query.iter_mut() {
+            let held = charge.held.elapsed_secs();
+            let weapon = arsenal.get(proj.weapon);
+            // Already dropped by `aim` this frame
+            if weapon.curve.fizzled(held) {
+                continue;
+            }
+            proj.size = weapon.curve.release_size(held, weapon.max_charge);
+            stats.shots_fired += 1;
             commands
                 .entity(p)
                 .remove::<RigidBody>()
@@ -203,54 +272,233 @@ fn mouse_input(
                 .insert(RigidBody::Dynamic)
                 .insert(Fired);
         }
+    } else if weapon.charge && selected.repeat.is_some() {
+        // Switched to a charging weapon with the trigger still held
+        selected.repeat = None;
+    } else if let Some(timer) = selected.repeat.as_mut() {
+        for _ in 0..timer.tick(time.delta()).times_finished_this_tick() {
+            fire(&mut commands, &texture_assets, &arsenal, slot, muzzle);
+            stats.shots_fired += 1;
+        }
     }
 }
 
-fn projectile_explode(commands: &mut Commands, projectile_entity: Entity) {
+/// Lets a size 1 shot go straight away, for weapons that don't charge
+fn fire(
+    commands: &mut Commands,
+    texture_assets: &TextureAssets,
+    arsenal: &Arsenal,
+    slot: usize,
+    translation: Vec3,
+) {
+    spawn_shot(
+        commands,
+        texture_assets,
+        arsenal,
+        slot,
+        1,
+        translation,
+        RigidBody::Dynamic,
+    )
+    .insert(Fired);
+}
+
+/// Swaps the projectile for its weapon's explosion, a full charge clears a
+/// cluster. Weapons without a blast just vanish
+fn projectile_explode(
+    commands: &mut Commands,
+    texture_assets: &TextureAssets,
+    weapon: &Weapon,
+    projectile_entity: Entity,
+    projectile: &PlayerProjectile,
+    transform: &Transform,
+) {
     commands.entity(projectile_entity).despawn();
+
+    if weapon.blast_scale <= 0.0 {
+        return;
+    }
+    let power = ((projectile.size as f32 * weapon.blast_scale).round() as i32).max(1);
+    spawn_explosion(
+        commands,
+        texture_assets,
+        transform.translation,
+        power,
+        0,
+        weapon.blast_duration,
+    );
 }
 
 fn projectile_timeouts(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Timeout), Without<Charging>>,
+    mut query: Query<(Entity, &mut Timeout, &PlayerProjectile, &Transform), Without<Charging>>,
+    texture_assets: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
     time: Res<Time>,
 ) {
-    for (entity, mut timeout) in query.iter_mut() {
+    for (entity, mut timeout, projectile, transform) in query.iter_mut() {
         if timeout.timer.finished() {
-            projectile_explode(&mut commands, entity);
+            projectile_explode(
+                &mut commands,
+                &texture_assets,
+                arsenal.get(projectile.weapon),
+                entity,
+                projectile,
+                transform,
+            );
         } else {
             timeout.timer.tick(time.delta());
         }
     }
 }
 
+/// Fired projectiles go off on anything solid, ones still charging only when
+/// an enemy runs into them. Piercing shots go off once they've run out of
+/// enemies to pass through, bouncing ones once they've run out of ricochets
 fn projectile_destruction(
     mut commands: Commands,
-    mut proj_query: Query<(Entity, &CollidingEntities), With<PlayerProjectile>>,
-    enemy_query: Query<Entity, (With<Enemy>, Without<EnemyProjectile>)>,
+    mut proj_query: Query<(
+        Entity,
+        &PlayerProjectile,
+        &Transform,
+        &CollidingEntities,
+        Has<Charging>,
+        Option<&mut Ricochets>,
+        Option<&Fragment>,
+    )>,
+    enemy_query: Query<(), (With<Enemy>, Without<EnemyProjectile>)>,
+    ground_query: Query<(), With<Ground>>,
+    fragments: Query<&Fragment>,
+    texture_assets: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
+    mut stats: ResMut<RunStats>,
+    mut counted: Local<HashSet<Entity>>,
 ) {
-    for (proj_entity, colliding_entities) in proj_query.iter_mut() {
-        for e in colliding_entities.iter() {
-            for enemy_entity in enemy_query.iter() {
-                if &enemy_entity == e {
-                    projectile_explode(&mut commands, proj_entity);
-                    break;
+    // Clusters that already scored a hit, for as long as any fragment is left
+    counted.retain(|shot| fragments.iter().any(|fragment| fragment.shot == *shot));
+
+    for (proj_entity, projectile, transform, colliding_entities, charging, ricochets, fragment) in
+        proj_query.iter_mut()
+    {
+        let weapon = arsenal.get(projectile.weapon);
+        let touching_enemy = colliding_entities.iter().any(|e| enemy_query.contains(*e));
+        let touching_ground =
+            !charging && colliding_entities.iter().any(|e| ground_query.contains(*e));
+
+        let hit_enemy = match weapon.behaviour {
+            Behaviour::Pierce { hits } => projectile.hits.len() > hits as usize,
+            _ => touching_enemy,
+        };
+        let hit_ground = match ricochets {
+            Some(mut ricochets) => {
+                let bounced = touching_ground && !ricochets.touching;
+                ricochets.touching = touching_ground;
+                if bounced && ricochets.left > 0 {
+                    ricochets.left -= 1;
+                    false
+                } else {
+                    bounced
                 }
             }
+            None => touching_ground,
+        };
+        if !hit_enemy && !hit_ground {
+            continue;
         }
+
+        let hit = touching_enemy || !projectile.hits.is_empty();
+        // A cluster was one shot, however many of its fragments land
+        let counts = match fragment {
+            Some(fragment) => hit && counted.insert(fragment.shot),
+            None => hit,
+        };
+        if counts {
+            stats.shots_hit += 1;
+        }
+        projectile_explode(
+            &mut commands,
+            &texture_assets,
+            weapon,
+            proj_entity,
+            projectile,
+            transform,
+        );
     }
 }
 
+/// Takes the hardest hit among everything touching the player, then deals
+/// with every attacker according to its `ContactResponse`
 fn hit(
+    mut commands: Commands,
     mut state: ResMut<NextState<GameState>>,
-    // mut time: ResMut<Time>,
-    query: Query<&CollidingEntities, With<Player>>,
+    settings: Res<PlayerSettings>,
+    mut query: Query<
+        (Entity, &mut Health, &CollidingEntities),
+        (With<Player>, Without<Invulnerable>),
+    >,
+    mut attackers: Query<(
+        &ContactDamage,
+        &GlobalTransform,
+        Option<&Parent>,
+        Option<&mut ExternalImpulse>,
+    )>,
 ) {
-    for colliding_entities in query.iter() {
-        if !colliding_entities.is_empty() {
-            state.set(GameState::Menu);
-            // time.pause();
+    for (player, mut health, colliding_entities) in query.iter_mut() {
+        let mut damage = 0;
+
+        for &entity in colliding_entities.iter() {
+            let Ok((contact, transform, parent, impulse)) = attackers.get_mut(entity) else {
+                continue;
+            };
+            damage = damage.max(contact.damage);
+
+            match contact.response {
+                ContactResponse::Despawn => {
+                    let root = parent.map_or(entity, |p| p.get());
+                    commands.entity(root).despawn_recursive();
+                }
+                ContactResponse::Knockback => {
+                    if let Some(mut impulse) = impulse {
+                        let away = (transform.translation().x - PLAYER_CENTER.x).signum();
+                        impulse.apply_impulse(Vec2::new(away, 0.5) * contact.knockback);
+                    }
+                }
+                ContactResponse::Nothing => {}
+            }
+        }
+
+        if damage <= 0 {
+            continue;
+        }
+
+        health.current -= damage;
+        if health.current <= 0 {
+            state.set(GameState::GameOver);
             return;
         }
+
+        commands.entity(player).insert(Invulnerable {
+            timer: Timer::from_seconds(settings.invulnerability, TimerMode::Once),
+        });
+    }
+}
+
+fn invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<PlayerSettings>,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility), With<Player>>,
+) {
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        invulnerable.timer.tick(time.delta());
+
+        if invulnerable.timer.finished() {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else if (invulnerable.timer.elapsed_secs() / settings.flicker) as u32 % 2 == 0 {
+            *visibility = Visibility::Hidden;
+        } else {
+            *visibility = Visibility::Inherited;
+        }
     }
 }
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+/// How a held shot grows, e.g. `curve = { type = "ramp", time = 1.2 }`
+#[derive(serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ChargeCurve {
+    /// Swells to full and back down every `period` seconds for as long as
+    /// it's held
+    Oscillate { period: f32 },
+    /// Fills over `time` seconds and stays full
+    Ramp { time: f32 },
+    /// Fills over `time` seconds, then fizzles out if it's still held `grace`
+    /// seconds later
+    Overcharge { time: f32, grace: f32 },
+    /// Fills over `time` seconds. Letting go within `window` seconds of it
+    /// filling adds `bonus` to the size
+    Perfect { time: f32, window: f32, bonus: i32 },
+}
+
+impl Default for ChargeCurve {
+    /// sin² of the seconds held
+    fn default() -> Self {
+        ChargeCurve::Oscillate { period: PI }
+    }
+}
+
+/// Where a charge is along its curve, for the meter
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) enum ChargeZone {
+    #[default]
+    Filling,
+    Full,
+    /// Let go now for the bonus
+    Perfect,
+    /// Past full and about to fizzle
+    Overcharged,
+}
+
+impl ChargeCurve {
+    /// The curve divides by `period` or `time`, so they have to be above 0.
+    /// `grace` and `window` can't be negative
+    pub fn validate(self) -> Result<(), String> {
+        let ((name, duration), leeway) = match self {
+            ChargeCurve::Oscillate { period } => (("period", period), None),
+            ChargeCurve::Ramp { time } => (("time", time), None),
+            ChargeCurve::Overcharge { time, grace } => (("time", time), Some(("grace", grace))),
+            ChargeCurve::Perfect { time, window, .. } => (("time", time), Some(("window", window))),
+        };
+
+        if duration > 0.0 {
+            match leeway {
+                Some((_, value)) if value >= 0.0 => Ok(()),
+                Some((name, value)) => Err(format!("{name} is {value}, it can't be negative")),
+                None => Ok(()),
+            }
+        } else {
+            Err(format!("{name} is {duration}, it has to be above 0"))
+        }
+    }
+
+    /// 0 to 1 after `elapsed` seconds held
+    pub fn level(self, elapsed: f32) -> f32 {
+        match self {
+            ChargeCurve::Oscillate { period } => (elapsed * PI / period).sin().powi(2),
+            ChargeCurve::Ramp { time }
+            | ChargeCurve::Overcharge { time, .. }
+            | ChargeCurve::Perfect { time, .. } => (elapsed / time).min(1.0),
+        }
+    }
+
+    pub fn zone(self, elapsed: f32) -> ChargeZone {
+        match self {
+            ChargeCurve::Oscillate { .. } => ChargeZone::Filling,
+            ChargeCurve::Ramp { time } if elapsed >= time => ChargeZone::Full,
+            ChargeCurve::Overcharge { time, .. } if elapsed >= time => ChargeZone::Overcharged,
+            ChargeCurve::Perfect { time, window, .. } if elapsed >= time => {
+                if elapsed <= time + window {
+                    ChargeZone::Perfect
+                } else {
+                    ChargeZone::Full
+                }
+            }
+            _ => ChargeZone::Filling,
+        }
+    }
+
+    /// Held too long, the shot is lost
+    pub fn fizzled(self, elapsed: f32) -> bool {
+        matches!(self, ChargeCurve::Overcharge { time, grace } if elapsed > time + grace)
+    }
+
+    /// Size while held, from 1 up to `max`
+    pub fn size(self, elapsed: f32, max: i32) -> i32 {
+        (self.level(elapsed) * (max - 1) as f32).round() as i32 + 1
+    }
+
+    /// Size when let go, with the bonus for a perfect release
+    pub fn release_size(self, elapsed: f32, max: i32) -> i32 {
+        let bonus = match self {
+            ChargeCurve::Perfect { bonus, .. } if self.zone(elapsed) == ChargeZone::Perfect => {
+                bonus
+            }
+            _ => 0,
+        };
+        self.size(elapsed, max) + bonus
+    }
+}
+
+/// The shot being charged right now, read by the HUD
+#[derive(Resource, Clone, Copy, Default, PartialEq)]
+pub(crate) struct ChargeMeter {
+    pub charging: bool,
+    pub level: f32,
+    pub zone: ChargeZone,
+}
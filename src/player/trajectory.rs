@@ -0,0 +1,55 @@
+use super::{launch_velocity, Arsenal, Charging, PlayerProjectile};
+use crate::actions::Actions;
+use crate::preferences::Preferences;
+use crate::PhysicsLayers;
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+/// Seconds between points on the arc
+const STEP: f32 = 1.0 / 30.0;
+const COLOR: Color = Color::rgba(0.6, 1.0, 0.6, 0.6);
+
+/// Follows a charging shot's path as if it were let go this frame, drawing
+/// every other step so the arc comes out dotted, and marks where it first
+/// hits the ground or a step
+pub(super) fn draw_trajectory(
+    preferences: Res<Preferences>,
+    arsenal: Res<Arsenal>,
+    gravity: Res<Gravity>,
+    actions: Res<Actions>,
+    spatial_query: SpatialQuery,
+    query: Query<(&PlayerProjectile, &Transform), With<Charging>>,
+    mut gizmos: Gizmos,
+) {
+    if !preferences.trajectory_preview {
+        return;
+    }
+
+    let filter = SpatialQueryFilter::from_mask(PhysicsLayers::Ground);
+
+    for (projectile, transform) in query.iter() {
+        let weapon = arsenal.get(projectile.weapon);
+        let steps = (weapon.timeout / STEP) as usize;
+        let mut position = transform.translation.truncate();
+        let mut velocity = launch_velocity(actions.direction(), projectile.size) * weapon.speed;
+
+        for step in 0..steps {
+            velocity += gravity.0 * weapon.gravity_scale * STEP;
+            let next = position + velocity * STEP;
+
+            if let Ok((direction, length)) = Direction2d::new_and_length(next - position) {
+                if let Some(hit) =
+                    spatial_query.cast_ray(position, direction, length, true, filter.clone())
+                {
+                    gizmos.circle_2d(position + *direction * hit.time_of_impact, 0.3, COLOR);
+                    break;
+                }
+            }
+
+            if step % 2 == 0 {
+                gizmos.line_2d(position, next, COLOR);
+            }
+            position = next;
+        }
+    }
+}
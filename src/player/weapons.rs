@@ -0,0 +1,348 @@
+use super::charge::ChargeCurve;
+use super::{Charging, PlayerProjectile, Timeout};
+use crate::actions::Actions;
+use crate::loading::TextureAssets;
+use crate::world::ArenaEntity;
+use crate::{DynamicActorBundle, PhysicsLayers};
+use bevy::{prelude::*, reflect::TypePath};
+use bevy_xpbd_2d::prelude::*;
+
+/// Every weapon the player can switch between, in slot order
+#[derive(serde::Deserialize, Clone, TypePath, Asset, Resource)]
+pub struct Arsenal {
+    #[serde(rename = "weapon")]
+    weapons: Vec<Weapon>,
+}
+
+impl Default for Arsenal {
+    fn default() -> Self {
+        Self {
+            weapons: vec![Weapon::default()],
+        }
+    }
+}
+
+impl Arsenal {
+    pub(crate) fn len(&self) -> usize {
+        self.weapons.len()
+    }
+
+    /// Falls back to the first weapon, there's always at least one
+    pub(crate) fn get(&self, slot: usize) -> &Weapon {
+        self.weapons.get(slot).unwrap_or(&self.weapons[0])
+    }
+
+    /// Puts back the default charge curve where a weapon's can't work, and
+    /// keeps full charges at least size 1. Returns what was wrong
+    pub(crate) fn repair(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for weapon in self.weapons.iter_mut() {
+            if let Err(e) = weapon.curve.validate() {
+                errors.push(format!("{} curve {e}, using the default", weapon.name));
+                weapon.curve = ChargeCurve::default();
+            }
+            if weapon.max_charge < 1 {
+                errors.push(format!(
+                    "{} max_charge is {}, it has to be at least 1",
+                    weapon.name, weapon.max_charge
+                ));
+                weapon.max_charge = 1;
+            }
+        }
+        errors
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct Weapon {
+    pub name: String,
+    pub color: [f32; 3],
+    /// Collider radius
+    pub radius: f32,
+    /// Holding the trigger charges the shot, otherwise every shot is size 1
+    pub charge: bool,
+    /// How the size grows while it's held
+    pub curve: ChargeCurve,
+    /// Size of a full charge
+    pub max_charge: i32,
+    /// Seconds between shots while the trigger is held, 0 for one per press.
+    /// Only for weapons that don't charge
+    pub repeat: f32,
+    /// Multiplies the launch speed
+    pub speed: f32,
+    pub gravity_scale: f32,
+    /// Seconds before it goes off on its own
+    pub timeout: f32,
+    /// Explosion power per point of size, 0 for no explosion at all
+    pub blast_scale: f32,
+    /// Seconds the explosion lasts
+    pub blast_duration: f32,
+    pub behaviour: Behaviour,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            name: "Ball".into(),
+            color: [0.0, 1.0, 0.0],
+            radius: 0.5,
+            charge: true,
+            curve: ChargeCurve::default(),
+            max_charge: 5,
+            repeat: 0.0,
+            speed: 1.0,
+            gravity_scale: 3.0,
+            timeout: 3.0,
+            blast_scale: 1.0,
+            blast_duration: 0.4,
+            behaviour: Behaviour::Impact,
+        }
+    }
+}
+
+impl Weapon {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color[0], self.color[1], self.color[2])
+    }
+}
+
+/// What the shot does once it's let go, e.g. `behaviour = { type = "pierce", hits = 3 }`
+#[derive(serde::Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum Behaviour {
+    /// Goes off on the first enemy or ground it touches
+    #[default]
+    Impact,
+    /// Splits into `fragments` smaller shots at the top of its arc, fanned
+    /// out over `spread` degrees. The fragments go off on impact
+    Cluster { fragments: u32, spread: f32 },
+    /// Passes through `hits` enemies and goes off on the next, or the ground
+    Pierce { hits: u32 },
+    /// Ricochets off the ground `ricochets` times, going off on the next
+    /// bounce or any enemy
+    Bounce { ricochets: u32 },
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct SelectedWeapon {
+    /// Slot of the weapon the next shot uses
+    pub slot: usize,
+    /// Runs while the trigger is held on a weapon that repeats
+    pub repeat: Option<Timer>,
+}
+
+/// A cluster shot's fragment, which never splits again
+#[derive(Component)]
+pub(crate) struct Fragment {
+    /// The cluster it split from, long since despawned
+    pub shot: Entity,
+}
+
+/// Ground bounces a bouncing shot has left
+#[derive(Component)]
+pub(crate) struct Ricochets {
+    pub left: u32,
+    /// Touching the ground last frame, so one bounce isn't counted twice
+    pub touching: bool,
+}
+
+/// Spawns a shot from `weapon` at `translation`. Static shots sit still until
+/// they're let go
+pub(crate) fn spawn_shot<'a>(
+    commands: &'a mut Commands,
+    texture_assets: &TextureAssets,
+    arsenal: &Arsenal,
+    slot: usize,
+    size: i32,
+    translation: Vec3,
+    rigidbody: RigidBody,
+) -> EntityCommands<'a> {
+    let weapon = arsenal.get(slot);
+
+    let mut shot = commands.spawn((
+        SpriteBundle {
+            texture: texture_assets.circle.clone(),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(weapon.radius * 2.0)),
+                color: weapon.color(),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(translation),
+            ..Default::default()
+        },
+        PlayerProjectile {
+            size,
+            weapon: slot,
+            hits: Vec::new(),
+        },
+        ArenaEntity,
+        DynamicActorBundle {
+            rigidbody,
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            collider: Collider::circle(weapon.radius),
+            collision_layers: CollisionLayers::new(
+                [PhysicsLayers::PlayerProj],
+                [
+                    PhysicsLayers::Ground,
+                    PhysicsLayers::Enemy,
+                    PhysicsLayers::EnemyProj,
+                ],
+            ),
+            mass: Mass(50.0),
+            friction: Friction::ZERO,
+            restitution: Restitution::PERFECTLY_ELASTIC,
+            ..Default::default()
+        },
+        Timeout {
+            timer: Timer::from_seconds(weapon.timeout, TimerMode::Once),
+        },
+        GravityScale(weapon.gravity_scale),
+    ));
+
+    match weapon.behaviour {
+        Behaviour::Pierce { .. } => {
+            shot.insert(Sensor);
+        }
+        Behaviour::Bounce { ricochets } => {
+            shot.insert(Ricochets {
+                left: ricochets,
+                touching: false,
+            });
+        }
+        Behaviour::Impact | Behaviour::Cluster { .. } => {}
+    }
+
+    shot
+}
+
+/// Cluster shots break up once they stop climbing
+pub(super) fn split_clusters(
+    mut commands: Commands,
+    texture_assets: Res<TextureAssets>,
+    arsenal: Res<Arsenal>,
+    query: Query<
+        (Entity, &PlayerProjectile, &Transform, &LinearVelocity),
+        (Without<Charging>, Without<super::Fired>, Without<Fragment>),
+    >,
+) {
+    for (entity, projectile, transform, velocity) in query.iter() {
+        let Behaviour::Cluster { fragments, spread } = arsenal.get(projectile.weapon).behaviour
+        else {
+            continue;
+        };
+        if velocity.y > 0.0 {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+
+        let size = (projectile.size + 1) / 2;
+        let step = if fragments > 1 {
+            spread.to_radians() / (fragments - 1) as f32
+        } else {
+            0.0
+        };
+        for i in 0..fragments {
+            let angle = step * i as f32 - spread.to_radians() / 2.0;
+            spawn_shot(
+                &mut commands,
+                &texture_assets,
+                &arsenal,
+                projectile.weapon,
+                size,
+                transform.translation,
+                RigidBody::Dynamic,
+            )
+            .insert((
+                Fragment { shot: entity },
+                LinearVelocity(Vec2::from_angle(angle).rotate(velocity.0)),
+                Transform::from_translation(transform.translation).with_scale(transform.scale),
+                SleepingDisabled,
+            ));
+        }
+    }
+}
+
+pub(super) fn switch_weapon(
+    arsenal: Res<Arsenal>,
+    mut selected: ResMut<SelectedWeapon>,
+    mut actions: ResMut<Actions>,
+) {
+    if let Some(switch) = actions.switch_weapon.take() {
+        let slot = switch.resolve(selected.slot, arsenal.len());
+        if slot < arsenal.len() && slot != selected.slot {
+            selected.slot = slot;
+        }
+    }
+}
+
+pub(super) fn reset_weapon(mut selected: ResMut<SelectedWeapon>) {
+    *selected = SelectedWeapon::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemies::Enemy;
+    use crate::score::RunStats;
+    use bevy::ecs::system::CommandQueue;
+
+    #[test]
+    fn cluster_scores_one_hit() {
+        let mut app = App::new();
+        app.insert_resource(Arsenal {
+            weapons: vec![Weapon {
+                behaviour: Behaviour::Cluster {
+                    fragments: 3,
+                    spread: 30.0,
+                },
+                ..default()
+            }],
+        })
+        .insert_resource(TextureAssets::default())
+        .insert_resource(RunStats {
+            shots_fired: 1,
+            ..default()
+        })
+        .add_systems(
+            Update,
+            (split_clusters, super::super::projectile_destruction).chain(),
+        );
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        let arsenal = app.world.resource::<Arsenal>();
+        let texture_assets = app.world.resource::<TextureAssets>();
+        spawn_shot(
+            &mut commands,
+            texture_assets,
+            arsenal,
+            0,
+            3,
+            Vec3::ZERO,
+            RigidBody::Dynamic,
+        )
+        .insert(LinearVelocity(Vec2::new(1.0, -1.0)));
+        queue.apply(&mut app.world);
+
+        // Past the top of its arc, so it splits
+        app.update();
+        let enemy = app.world.spawn(Enemy::default()).id();
+        let mut fragments = app
+            .world
+            .query_filtered::<&mut CollidingEntities, With<Fragment>>();
+        let mut count = 0;
+        for mut colliding in fragments.iter_mut(&mut app.world) {
+            colliding.insert(enemy);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+
+        // Every fragment lands on the same enemy at once
+        app.update();
+        let stats = app.world.resource::<RunStats>();
+        assert_eq!(stats.shots_hit, 1);
+        assert!(stats.shots_hit <= stats.shots_fired);
+    }
+}
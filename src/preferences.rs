@@ -0,0 +1,45 @@
+use crate::user_data;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = "preferences.toml";
+
+/// Options set from the menu, saved next to the controls
+#[derive(Resource, serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Preferences {
+    /// Draw the arc a charging shot will follow
+    pub trajectory_preview: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            trajectory_preview: true,
+        }
+    }
+}
+
+impl Preferences {
+    /// In the user's config directory, next to the saved bindings
+    pub fn config_path() -> PathBuf {
+        user_data::config_path(FILE_NAME)
+    }
+
+    /// Writes to `config_path`, logging rather than failing
+    pub(crate) fn store(&self) {
+        user_data::save(&Preferences::config_path(), self, "preferences");
+    }
+}
+
+/// Reads the saved preferences, only added with a window, headless runs use
+/// the defaults
+pub struct PreferencesPlugin;
+
+impl Plugin for PreferencesPlugin {
+    fn build(&self, app: &mut App) {
+        let preferences: Preferences = user_data::load(&Preferences::config_path(), "preferences");
+
+        app.insert_resource(preferences);
+    }
+}
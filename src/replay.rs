@@ -0,0 +1,475 @@
+use crate::actions::{ActionSet, Actions, WeaponSwitch};
+use crate::difficulty::{Difficulty, DifficultySettings, Preset};
+use crate::events::{self, SpawnRatesOverTime, TimeTable};
+use crate::headless::{Headless, SimulationStep};
+use crate::loading::{FontAssets, GameData};
+use crate::player::{Arsenal, SelectedWeapon};
+use crate::rng::{self, GameSeed};
+use crate::user_data;
+use crate::waves::{self, WaveDirector, WaveScript};
+use crate::{running, GameState};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::Instant;
+use std::time::Duration;
+
+/// Every finished run is written here, under [`user_data::dir`]
+const LAST_REPLAY: &str = "replays/last.replay.toml";
+
+/// A recorded run: the seed, difficulty, spawn table and wave script it started with, and the
+/// `Actions` handed to the player on every frame. Insert one before adding
+/// `GamePlugin` to watch it, or point the `LAST_STANZA_REPLAY` environment
+/// variable at a file
+#[derive(Resource, serde::Serialize, serde::Deserialize, Clone)]
+pub struct Replay {
+    #[serde(with = "seed_string")]
+    pub seed: u64,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub preset: Preset,
+    pub spawn_rates: TimeTable,
+    #[serde(default)]
+    pub waves: WaveScript,
+    #[serde(with = "frame_track")]
+    pub frames: Vec<InputFrame>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct InputFrame {
+    /// Frame time in nanoseconds. Playback steps the clock by exactly this much
+    pub dt: u64,
+    /// Only stored when the aim point moved since the previous frame
+    pub aim: Option<[f32; 2]>,
+    /// `Actions::charge`
+    pub press: bool,
+    /// `Actions::fire`
+    pub release: bool,
+    /// Weapon slot switched to on this frame, however the switch was asked for
+    pub weapon: Option<usize>,
+}
+
+/// TOML integers are signed, so the seed is written the way it's logged
+mod seed_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Frames are written as one array per field rather than a table per frame:
+/// every frame's `dt`, then only the frames where something happened, by index,
+/// e.g. `press = [30, 95]` or `aim = [[0, 0.0, 9.75]]`
+mod frame_track {
+    use super::InputFrame;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize, Default)]
+    #[serde(default)]
+    struct Track {
+        dt: Vec<u64>,
+        aim: Vec<(usize, f32, f32)>,
+        press: Vec<usize>,
+        release: Vec<usize>,
+        weapon: Vec<(usize, usize)>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        frames: &[InputFrame],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut track = Track::default();
+        for (i, frame) in frames.iter().enumerate() {
+            track.dt.push(frame.dt);
+            if let Some([x, y]) = frame.aim {
+                track.aim.push((i, x, y));
+            }
+            if frame.press {
+                track.press.push(i);
+            }
+            if frame.release {
+                track.release.push(i);
+            }
+            if let Some(slot) = frame.weapon {
+                track.weapon.push((i, slot));
+            }
+        }
+        track.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<InputFrame>, D::Error> {
+        let track = Track::deserialize(deserializer)?;
+        let mut frames: Vec<InputFrame> = track
+            .dt
+            .iter()
+            .map(|&dt| InputFrame {
+                dt,
+                ..Default::default()
+            })
+            .collect();
+
+        let count = frames.len();
+        let mut frame = |i: usize| {
+            frames
+                .get_mut(i)
+                .ok_or_else(|| D::Error::custom(format!("input on frame {i}, there are {count}")))
+        };
+        for (i, x, y) in track.aim {
+            frame(i)?.aim = Some([x, y]);
+        }
+        for i in track.press {
+            frame(i)?.press = true;
+        }
+        for i in track.release {
+            frame(i)?.release = true;
+        }
+        for (i, slot) in track.weapon {
+            frame(i)?.weapon = Some(slot);
+        }
+
+        Ok(frames)
+    }
+}
+
+/// Present while a replay drives the game instead of the player
+#[derive(Resource)]
+pub(crate) struct Playback {
+    replay: Replay,
+    frame: usize,
+    speed: f32,
+    paused: bool,
+    /// Advance a single frame while paused
+    step: bool,
+    /// Wall clock time owed towards the next recorded frame
+    banked: Duration,
+}
+
+impl Playback {
+    fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            frame: 0,
+            speed: 1.0,
+            paused: false,
+            step: false,
+            banked: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct Recording {
+    replay: Replay,
+    last_aim: Option<Vec2>,
+}
+
+#[derive(Component)]
+struct ReplayOverlay;
+
+pub struct ReplayPlugin {
+    pub headless: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let replay = app.world.remove_resource::<Replay>().or_else(|| {
+            let path = std::env::var("LAST_STANZA_REPLAY").ok()?;
+            user_data::read::<Replay>(&path)
+                .map_err(|e| error!("Couldn't load replay {path}: {e}"))
+                .ok()
+        });
+
+        if let Some(replay) = replay {
+            info!("Playing back {} frames", replay.frames.len());
+            app.insert_resource(GameSeed(replay.seed))
+                .insert_resource(replay.difficulty)
+                .insert_resource(Playback::new(replay));
+        }
+
+        app.add_systems(
+            OnEnter(GameState::Menu),
+            start_playback.run_if(resource_exists::<Playback>),
+        )
+        .add_systems(
+            OnEnter(GameState::Playing),
+            (
+                start_recording
+                    .after(rng::reseed)
+                    .run_if(not(resource_exists::<Playback>)),
+                playback_preset
+                    .before(events::load)
+                    .run_if(resource_exists::<Playback>),
+                playback_spawn_rates
+                    .after(events::load)
+                    .run_if(resource_exists::<Playback>),
+                playback_waves
+                    .after(waves::load)
+                    .run_if(resource_exists::<Playback>),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                playback_input
+                    .in_set(ActionSet::Read)
+                    .run_if(resource_exists::<Playback>),
+                record_input
+                    .in_set(ActionSet::Observe)
+                    .run_if(resource_exists::<Recording>),
+            )
+                .run_if(running),
+        )
+        .add_systems(
+            Last,
+            pace_playback
+                .run_if(in_state(GameState::Playing))
+                .run_if(resource_exists::<Playback>),
+        )
+        .add_systems(
+            OnExit(GameState::Playing),
+            (
+                save_recording.run_if(resource_exists::<Recording>),
+                stop_playback.run_if(resource_exists::<Playback>),
+            ),
+        );
+
+        if !self.headless {
+            app.add_systems(
+                OnEnter(GameState::Playing),
+                spawn_overlay.run_if(resource_exists::<Playback>),
+            )
+            .add_systems(
+                Update,
+                (viewer_controls, update_overlay)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(resource_exists::<Playback>),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_overlay);
+        }
+    }
+}
+
+/// Skips the menu and steps the first frame by its recorded time
+fn start_playback(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(first) = playback.replay.frames.first() else {
+        warn!("Replay has no frames");
+        commands.remove_resource::<Playback>();
+        return;
+    };
+
+    *strategy = TimeUpdateStrategy::ManualDuration(Duration::from_nanos(first.dt));
+    next_state.set(GameState::Playing);
+}
+
+/// The recorded multipliers, in case the presets have been retuned since
+fn playback_preset(playback: Res<Playback>, mut difficulty: ResMut<DifficultySettings>) {
+    difficulty.preset = playback.replay.preset.clone();
+}
+
+fn playback_spawn_rates(mut commands: Commands, playback: Res<Playback>) {
+    commands.insert_resource(SpawnRatesOverTime::from(
+        playback.replay.spawn_rates.clone(),
+    ));
+}
+
+fn playback_waves(mut commands: Commands, playback: Res<Playback>) {
+    commands.insert_resource(WaveDirector::from(playback.replay.waves.clone()));
+}
+
+fn playback_input(mut playback: ResMut<Playback>, mut actions: ResMut<Actions>) {
+    let Some(frame) = playback.replay.frames.get(playback.frame).copied() else {
+        return;
+    };
+    playback.frame += 1;
+
+    if let Some(aim) = frame.aim {
+        actions.aim = Vec2::from(aim);
+    }
+    actions.charge |= frame.press;
+    actions.fire |= frame.release;
+    if let Some(slot) = frame.weapon {
+        actions.switch_weapon = Some(WeaponSwitch::Slot(slot));
+    }
+}
+
+/// Decides whether the next frame plays a recorded frame or holds. Frames are
+/// never merged or split, so speed only changes how often they're played
+fn pace_playback(
+    mut playback: ResMut<Playback>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    headless: Option<Res<Headless>>,
+    mut last_wall: Local<Option<Instant>>,
+) {
+    let now = Instant::now();
+    let wall = last_wall
+        .replace(now)
+        .map_or(Duration::ZERO, |last| now - last);
+
+    let Some(next) = playback.replay.frames.get(playback.frame) else {
+        // The recording stopped without the player dying, e.g. the game was closed
+        next_state.set(GameState::Menu);
+        return;
+    };
+    let dt = Duration::from_nanos(next.dt);
+
+    let due = if headless.is_some() {
+        true
+    } else if playback.paused {
+        std::mem::take(&mut playback.step)
+    } else {
+        playback.banked += wall.mul_f32(playback.speed);
+        if playback.banked >= dt {
+            playback.banked = (playback.banked - dt).min(dt);
+            true
+        } else {
+            false
+        }
+    };
+
+    if due {
+        time.unpause();
+        *strategy = TimeUpdateStrategy::ManualDuration(dt);
+    } else {
+        time.pause();
+    }
+}
+
+fn stop_playback(
+    mut commands: Commands,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut time: ResMut<Time<Virtual>>,
+    step: Option<Res<SimulationStep>>,
+) {
+    commands.remove_resource::<Playback>();
+    *strategy = step.map_or(TimeUpdateStrategy::Automatic, |step| {
+        TimeUpdateStrategy::ManualDuration(step.0)
+    });
+    time.unpause();
+}
+
+fn start_recording(
+    mut commands: Commands,
+    seed: Res<GameSeed>,
+    game_data: Res<GameData>,
+    difficulty: Res<DifficultySettings>,
+    time_tables: Res<Assets<TimeTable>>,
+    wave_scripts: Res<Assets<WaveScript>>,
+) {
+    let Some(spawn_rates) = time_tables.get(&difficulty.spawn_rates) else {
+        return;
+    };
+    let waves = wave_scripts
+        .get(&game_data.waves)
+        .cloned()
+        .unwrap_or_default();
+
+    commands.insert_resource(Recording {
+        replay: Replay {
+            seed: seed.0,
+            difficulty: difficulty.difficulty,
+            preset: difficulty.preset.clone(),
+            spawn_rates: spawn_rates.clone(),
+            waves,
+            frames: Vec::new(),
+        },
+        last_aim: None,
+    });
+}
+
+fn record_input(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    arsenal: Res<Arsenal>,
+    selected: Res<SelectedWeapon>,
+    mut recording: ResMut<Recording>,
+) {
+    let aim = actions.aim;
+    let moved = recording.last_aim != Some(aim);
+    recording.last_aim = Some(aim);
+
+    recording.replay.frames.push(InputFrame {
+        dt: time.delta().as_nanos() as u64,
+        aim: moved.then_some(aim.into()),
+        press: actions.charge,
+        release: actions.fire,
+        weapon: actions
+            .switch_weapon
+            .map(|switch| switch.resolve(selected.slot, arsenal.len())),
+    });
+}
+
+fn save_recording(mut commands: Commands, recording: Res<Recording>) {
+    commands.remove_resource::<Recording>();
+
+    user_data::save(&user_data::path(LAST_REPLAY), &recording.replay, "replay");
+}
+
+fn viewer_controls(keys: Res<ButtonInput<KeyCode>>, mut playback: ResMut<Playback>) {
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        playback.step = true;
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        playback.speed = (playback.speed * 2.0).min(8.0);
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        playback.speed = (playback.speed / 2.0).max(0.125);
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font_assets.fantasque_sans.clone(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        ReplayOverlay,
+    ));
+}
+
+fn update_overlay(playback: Res<Playback>, mut query: Query<&mut Text, With<ReplayOverlay>>) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "REPLAY {}/{}  x{}{}   space: pause  .: step  [ ]: speed",
+            playback.frame,
+            playback.replay.frames.len(),
+            playback.speed,
+            if playback.paused { "  (paused)" } else { "" },
+        );
+    }
+}
+
+fn despawn_overlay(mut commands: Commands, query: Query<Entity, With<ReplayOverlay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,71 @@
+use crate::GameState;
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Seed for the current run. Insert it before adding `GamePlugin` to pin it for
+/// every run, otherwise it's read from the `LAST_STANZA_SEED` environment
+/// variable or picked afresh each run
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GameSeed(pub u64);
+
+/// The seed was given at startup, so every run replays it
+#[derive(Resource)]
+struct FixedSeed;
+
+/// Gameplay randomness, split into streams so that e.g. extra camera shake
+/// doesn't change which enemies spawn
+#[derive(Resource)]
+pub(crate) struct GameRng {
+    pub spawning: StdRng,
+    pub ai: StdRng,
+    pub cosmetic: StdRng,
+}
+
+impl GameRng {
+    fn from_seed(seed: u64) -> Self {
+        Self {
+            spawning: StdRng::seed_from_u64(seed),
+            ai: StdRng::seed_from_u64(seed ^ 0x5eed_0000_0000_a1a1),
+            cosmetic: StdRng::seed_from_u64(seed ^ 0x5eed_0000_0000_c0c0),
+        }
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let fixed = app.world.get_resource::<GameSeed>().copied().or_else(|| {
+            std::env::var("LAST_STANZA_SEED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(GameSeed)
+        });
+        let seed = match fixed {
+            Some(seed) => {
+                info!("Game seed: {}, for every run", seed.0);
+                app.insert_resource(FixedSeed);
+                seed
+            }
+            None => GameSeed(rand::thread_rng().gen()),
+        };
+
+        app.insert_resource(seed)
+            .insert_resource(GameRng::from_seed(seed.0))
+            .add_systems(OnEnter(GameState::Playing), reseed);
+    }
+}
+
+/// Every run starts the streams over, from the fixed seed if there is one so
+/// it reproduces every run, else from a new one
+pub(crate) fn reseed(
+    fixed: Option<Res<FixedSeed>>,
+    mut seed: ResMut<GameSeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    if fixed.is_none() {
+        seed.0 = rand::thread_rng().gen();
+        info!("Game seed: {}", seed.0);
+    }
+    *rng = GameRng::from_seed(seed.0);
+}
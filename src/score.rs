@@ -0,0 +1,163 @@
+use crate::enemies::{DeathCause, EnemyDied, EnemyKind};
+use crate::{running, GameState};
+use bevy::{prelude::*, utils::HashMap};
+
+/// How long a chain stays up in the HUD after its last kill
+const COMBO_DISPLAY_TIME: f32 = 1.5;
+
+/// Points and kills for the current run. It's only cleared when the next run
+/// starts, so the screens after a run can still read it
+#[derive(Resource, Default, Clone, Debug)]
+pub struct Score {
+    pub points: u32,
+    pub kills: HashMap<EnemyKind, u32>,
+    /// Longest chain of explosion kills this run
+    pub best_chain: u32,
+}
+
+impl Score {
+    pub fn total_kills(&self) -> u32 {
+        self.kills.values().sum()
+    }
+}
+
+/// Everything else the results screen reports on
+#[derive(Resource, Default, Clone, Debug)]
+pub struct RunStats {
+    /// Seconds of game time survived
+    pub survived: f32,
+    pub shots_fired: u32,
+    /// Shots that hit at least one enemy
+    pub shots_hit: u32,
+}
+
+impl RunStats {
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f32 / self.shots_fired as f32
+        }
+    }
+}
+
+/// The chain the latest explosion kill belonged to
+#[derive(Resource, Default)]
+pub(crate) struct Combo {
+    pub chain: u32,
+    pub timer: Timer,
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .init_resource::<RunStats>()
+            .init_resource::<Combo>()
+            .add_systems(OnEnter(GameState::Playing), reset)
+            .add_systems(
+                Update,
+                ((award, combo_timeout).chain(), survive).run_if(running),
+            );
+    }
+}
+
+fn reset(mut score: ResMut<Score>, mut stats: ResMut<RunStats>, mut combo: ResMut<Combo>) {
+    *score = Score::default();
+    *stats = RunStats::default();
+    *combo = Combo::default();
+}
+
+fn survive(time: Res<Time>, mut stats: ResMut<RunStats>) {
+    stats.survived += time.delta_seconds();
+}
+
+/// Projectile kills are worth more the bigger the charge, explosion kills are
+/// multiplied by their place in the chain. A crash while a chain is up was
+/// most likely knocked there by it, so it adds a link, otherwise it's worth
+/// the base points
+fn award(mut died_ev: EventReader<EnemyDied>, mut score: ResMut<Score>, mut combo: ResMut<Combo>) {
+    for e in died_ev.read() {
+        let multiplier = match e.cause {
+            DeathCause::Projectile { size } => size.max(1) as u32,
+            DeathCause::Explosion { chain, .. } => {
+                if chain >= combo.chain || combo.timer.finished() {
+                    combo.chain = chain;
+                }
+                combo.timer = Timer::from_seconds(COMBO_DISPLAY_TIME, TimerMode::Once);
+                score.best_chain = score.best_chain.max(chain);
+                chain + 1
+            }
+            DeathCause::Crash if combo.chain > 0 => {
+                combo.chain += 1;
+                combo.timer = Timer::from_seconds(COMBO_DISPLAY_TIME, TimerMode::Once);
+                score.best_chain = score.best_chain.max(combo.chain);
+                combo.chain + 1
+            }
+            DeathCause::Crash => 1,
+        };
+
+        score.points += e.points * multiplier;
+        *score.kills.entry(e.kind).or_default() += 1;
+    }
+}
+
+fn combo_timeout(time: Res<Time>, mut combo: ResMut<Combo>) {
+    combo.timer.tick(time.delta());
+    if combo.timer.just_finished() {
+        combo.chain = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_event::<EnemyDied>()
+            .init_resource::<Score>()
+            .init_resource::<Combo>()
+            .add_systems(Update, award);
+        app
+    }
+
+    fn died(app: &mut App, cause: DeathCause) {
+        app.world.send_event(EnemyDied {
+            entity: Entity::PLACEHOLDER,
+            kind: EnemyKind::Diver,
+            position: Vec2::ZERO,
+            overkill: 0,
+            points: 10,
+            cause,
+        });
+    }
+
+    #[test]
+    fn crash_alone_scores_base_points() {
+        let mut app = app();
+        died(&mut app, DeathCause::Crash);
+        app.update();
+
+        let score = app.world.resource::<Score>();
+        assert_eq!(score.points, 10);
+        assert_eq!(score.total_kills(), 1);
+        assert_eq!(score.best_chain, 0);
+        assert_eq!(app.world.resource::<Combo>().chain, 0);
+    }
+
+    #[test]
+    fn crash_extends_a_live_chain() {
+        let mut app = app();
+        died(&mut app, DeathCause::Explosion { power: 3, chain: 2 });
+        died(&mut app, DeathCause::Crash);
+        app.update();
+
+        let score = app.world.resource::<Score>();
+        assert_eq!(score.points, 10 * 3 + 10 * 4);
+        assert_eq!(score.total_kills(), 2);
+        assert_eq!(score.best_chain, 3);
+        assert_eq!(app.world.resource::<Combo>().chain, 3);
+    }
+}
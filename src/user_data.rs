@@ -0,0 +1,91 @@
+use bevy::log::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Used when there's no telling where the platform keeps per-user files
+const FALLBACK_DIR: &str = "saves";
+const GAME_DIR: &str = "last-stanza";
+
+/// Where the game keeps its records, so they don't depend on the working
+/// directory: `$XDG_DATA_HOME`, `~/.local/share`, `%APPDATA%` or
+/// `~/Library/Application Support` depending on the platform, in a
+/// `last-stanza` directory. Else `saves/` next to the game
+pub fn dir() -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".local/share")))
+    };
+
+    dir.map_or_else(|| PathBuf::from(FALLBACK_DIR), |dir| dir.join(GAME_DIR))
+}
+
+/// `file` under `dir`, e.g. `path("highscores.toml")`
+pub fn path(file: impl AsRef<Path>) -> PathBuf {
+    dir().join(file)
+}
+
+/// Where settings the player chose go: `$XDG_CONFIG_HOME`, `~/.config`,
+/// `%APPDATA%` or `~/Library/Preferences` depending on the platform, in a
+/// `last-stanza` directory. Else `saves/` next to the game
+pub fn config_dir() -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Preferences"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+
+    dir.map_or_else(|| PathBuf::from(FALLBACK_DIR), |dir| dir.join(GAME_DIR))
+}
+
+/// `file` under `config_dir`, e.g. `config_path("controls.toml")`
+pub fn config_path(file: impl AsRef<Path>) -> PathBuf {
+    config_dir().join(file)
+}
+
+/// Parses a TOML file the game wrote, for files that have no default to fall
+/// back on
+pub fn read<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(toml::from_str(&text)?)
+}
+
+/// Parses a TOML file the game wrote, e.g. `load(&path, "high scores")`.
+/// Missing is the default, unreadable is logged and the default too
+pub fn load<T: DeserializeOwned + Default>(path: &Path, what: &str) -> T {
+    if !path.exists() {
+        return T::default();
+    }
+    read(path).unwrap_or_else(|e| {
+        error!("Couldn't load {what} from {}: {e}", path.display());
+        T::default()
+    })
+}
+
+/// Writes `value` as TOML, making its directory first
+pub fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, toml::to_string(value)?)?;
+    Ok(())
+}
+
+/// `write`, logging rather than failing
+pub fn save<T: Serialize>(path: &Path, value: &T, what: &str) {
+    match write(path, value) {
+        Ok(()) => info!("Saved {what} to {}", path.display()),
+        Err(e) => error!("Couldn't save {what} to {}: {e}", path.display()),
+    }
+}
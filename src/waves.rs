@@ -0,0 +1,274 @@
+use crate::enemies::{
+    archetype::EnemyArchetypes, queue_spawn, spawn_point::SpawnPoints, Enemy, EnemyKind, Facing,
+    SpawnParams,
+};
+use crate::{loading::GameData, running, GameState};
+use bevy::{prelude::*, reflect::TypePath, time::Stopwatch};
+
+pub struct WavesPlugin;
+
+impl Plugin for WavesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WaveStarted>()
+            .add_event::<WaveEnded>()
+            .init_resource::<WaveDirector>()
+            .add_systems(OnEnter(GameState::Playing), load)
+            .add_systems(Update, run_waves.run_if(running));
+    }
+}
+
+#[derive(Event)]
+pub struct WaveStarted {
+    pub index: usize,
+    pub name: String,
+}
+
+/// Sent once everything in the wave has spawned and died
+#[derive(Event)]
+pub struct WaveEnded {
+    pub index: usize,
+    pub name: String,
+}
+
+/// Hand-written waves, run in order alongside the random spawner
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, TypePath, Asset)]
+pub struct WaveScript {
+    #[serde(default, rename = "wave")]
+    pub waves: Vec<Wave>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Wave {
+    #[serde(default)]
+    pub name: String,
+    /// Seconds into the run before the wave can start
+    #[serde(default)]
+    pub at: f32,
+    /// Also wait for every earlier wave to end and the field to be empty
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub after_clear: bool,
+    /// Hold the random spawner from `at` until the wave ends
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pause_random: bool,
+    #[serde(default, rename = "group")]
+    pub groups: Vec<SpawnGroup>,
+}
+
+/// A batch of one enemy kind within a wave
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct SpawnGroup {
+    pub enemy: EnemyKind,
+    #[serde(default = "one")]
+    pub count: u32,
+    #[serde(default)]
+    pub side: Side,
+    /// Seconds between each spawn in the group
+    #[serde(default)]
+    pub interval: f32,
+    /// Seconds after the wave starts before the first spawn
+    #[serde(default)]
+    pub delay: f32,
+    /// Archetype variant, rolled by weight when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Named spawn point, e.g. `"sky"`, instead of the archetype's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    #[default]
+    Random,
+    Left,
+    Right,
+    /// Alternate, starting from the left
+    Both,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+struct PendingSpawn {
+    at: f32,
+    kind: EnemyKind,
+    params: SpawnParams,
+}
+
+struct ActiveWave {
+    index: usize,
+    /// Latest first, so the next one due is at the end
+    pending: Vec<PendingSpawn>,
+}
+
+impl ActiveWave {
+    fn new(index: usize, wave: &Wave, now: f32) -> Self {
+        let mut pending = Vec::new();
+        for group in wave.groups.iter() {
+            for i in 0..group.count {
+                let facing = match group.side {
+                    Side::Random => None,
+                    Side::Left => Some(Facing::Left),
+                    Side::Right => Some(Facing::Right),
+                    Side::Both if i % 2 == 0 => Some(Facing::Left),
+                    Side::Both => Some(Facing::Right),
+                };
+
+                pending.push(PendingSpawn {
+                    at: now + group.delay + group.interval * i as f32,
+                    kind: group.enemy,
+                    params: SpawnParams {
+                        facing,
+                        variant: group.variant.clone(),
+                        wave: Some(index),
+                        point: group.point.clone(),
+                    },
+                });
+            }
+        }
+        pending.sort_by(|a, b| b.at.total_cmp(&a.at));
+
+        Self { index, pending }
+    }
+}
+
+/// Where this run is in the wave script
+#[derive(Resource, Default)]
+pub(crate) struct WaveDirector {
+    script: WaveScript,
+    /// Index of the next wave waiting to start
+    next: usize,
+    active: Vec<ActiveWave>,
+    elapsed: Stopwatch,
+}
+
+impl From<WaveScript> for WaveDirector {
+    fn from(script: WaveScript) -> Self {
+        Self {
+            script,
+            ..default()
+        }
+    }
+}
+
+impl WaveDirector {
+    fn wave(&self, index: usize) -> &Wave {
+        &self.script.waves[index]
+    }
+
+    fn holds_random_spawns(&self) -> bool {
+        let due = self
+            .script
+            .waves
+            .get(self.next)
+            .is_some_and(|wave| wave.pause_random && self.elapsed.elapsed_secs() >= wave.at);
+
+        due || self
+            .active
+            .iter()
+            .any(|active| self.wave(active.index).pause_random)
+    }
+}
+
+/// Run condition for the random spawner
+pub(crate) fn random_spawns(director: Res<WaveDirector>) -> bool {
+    !director.holds_random_spawns()
+}
+
+pub fn load(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    scripts: Res<Assets<WaveScript>>,
+    archetypes: Option<Res<EnemyArchetypes>>,
+    spawn_points: Res<SpawnPoints>,
+) {
+    let script = scripts.get(&game_data.waves).cloned().unwrap_or_default();
+
+    for wave in script.waves.iter() {
+        for point in wave.groups.iter().filter_map(|group| group.point.as_ref()) {
+            if !spawn_points.contains(point) {
+                warn!(
+                    "Wave {:?} spawns at unknown point {point:?}, the archetype's own is used",
+                    wave.name
+                );
+            }
+        }
+    }
+
+    if let Some(archetypes) = archetypes {
+        for wave in script.waves.iter() {
+            for group in wave.groups.iter() {
+                let Some(variant) = &group.variant else {
+                    continue;
+                };
+                if archetypes.variant(group.enemy, variant).is_none() {
+                    warn!(
+                        "Wave {:?} asks for unknown {:?} variant {variant:?}, it will be rolled",
+                        wave.name, group.enemy
+                    );
+                }
+            }
+        }
+    }
+
+    commands.insert_resource(WaveDirector::from(script));
+}
+
+fn run_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut director: ResMut<WaveDirector>,
+    enemies: Query<(), With<Enemy>>,
+    spawned: Query<&SpawnParams>,
+    mut started: EventWriter<WaveStarted>,
+    mut ended: EventWriter<WaveEnded>,
+) {
+    director.elapsed.tick(time.delta());
+    let now = director.elapsed.elapsed_secs();
+
+    while let Some(wave) = director.script.waves.get(director.next) {
+        let clear = director.active.is_empty() && enemies.is_empty();
+        if now < wave.at || (wave.after_clear && !clear) {
+            break;
+        }
+
+        started.send(WaveStarted {
+            index: director.next,
+            name: wave.name.clone(),
+        });
+        let active = ActiveWave::new(director.next, wave, now);
+        director.active.push(active);
+        director.next += 1;
+    }
+
+    for active in director.active.iter_mut() {
+        while active.pending.last().is_some_and(|spawn| spawn.at <= now) {
+            let spawn = active.pending.pop().unwrap();
+            queue_spawn(&mut commands, spawn.kind, spawn.params);
+        }
+    }
+
+    // Spawn markers carry the params too, so a wave isn't over between
+    // queueing its last enemy and that enemy appearing
+    let director = &mut *director;
+    director.active.retain(|active| {
+        let alive = spawned
+            .iter()
+            .any(|params| params.wave == Some(active.index));
+        if !active.pending.is_empty() || alive {
+            return true;
+        }
+
+        ended.send(WaveEnded {
+            index: active.index,
+            name: director.script.waves[active.index].name.clone(),
+        });
+        false
+    });
+}
@@ -1,3 +1,5 @@
+#[cfg(debug_assertions)]
+use crate::headless::Headless;
 use crate::{loading::TextureAssets, GameState, PhysicsLayers};
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
@@ -6,11 +8,20 @@ pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_world);
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            (cleanup_arena, spawn_world).chain(),
+        )
+        .add_systems(OnEnter(GameState::Menu), cleanup_arena);
 
         #[cfg(debug_assertions)]
         {
-            app.add_systems(Update, debug_world.run_if(in_state(GameState::Playing)));
+            app.add_systems(
+                Update,
+                debug_world
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<Headless>)),
+            );
         }
     }
 }
@@ -18,6 +29,11 @@ impl Plugin for WorldPlugin {
 #[derive(Component)]
 pub struct Ground;
 
+/// Belongs to a single run: the world, the player and everything fired or
+/// spawned during it. Despawned before the next run starts or on the way to the menu
+#[derive(Component, Default)]
+pub(crate) struct ArenaEntity;
+
 #[derive(Bundle)]
 struct WorldBundle {
     transform_bundle: TransformBundle,
@@ -94,6 +110,12 @@ fn debug_world(mut gizmos: Gizmos) {
     }
 }
 
+fn cleanup_arena(mut commands: Commands, query: Query<Entity, With<ArenaEntity>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 fn spawn_world(mut commands: Commands, texture_assets: Res<TextureAssets>) {
     let step_height = 2.0;
     let step_decrement = 6.4;
@@ -104,43 +126,49 @@ fn spawn_world(mut commands: Commands, texture_assets: Res<TextureAssets>) {
     let ground_shape = Vec2::new(100.0, 6.0);
 
     // Ground texture
-    commands.spawn(SpriteBundle {
-        texture: texture_assets.ground.clone(),
-        sprite: Sprite {
-            anchor: bevy::sprite::Anchor::TopCenter,
-            custom_size: Some(Vec2::new(30.0, 0.703125)),
+    commands
+        .spawn(SpriteBundle {
+            texture: texture_assets.ground.clone(),
+            sprite: Sprite {
+                anchor: bevy::sprite::Anchor::TopCenter,
+                custom_size: Some(Vec2::new(30.0, 0.703125)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(Vec3::ZERO),
             ..Default::default()
-        },
-        transform: Transform::from_translation(Vec3::ZERO),
-        ..Default::default()
-    });
+        })
+        .insert(ArenaEntity);
 
     // Ground collider
-    commands.spawn(WorldBundle {
-        transform_bundle: TransformBundle {
-            local: Transform::from_translation(pos),
+    commands
+        .spawn(WorldBundle {
+            transform_bundle: TransformBundle {
+                local: Transform::from_translation(pos),
+                ..Default::default()
+            },
+            collider: Collider::rectangle(ground_shape.x, ground_shape.y),
             ..Default::default()
-        },
-        collider: Collider::rectangle(ground_shape.x, ground_shape.y),
-        ..Default::default()
-    });
+        })
+        .insert(ArenaEntity);
 
     let mut step_shape = Vec2::new(0.0, step_height);
 
     // Ziggurat texture
-    commands.spawn(SpriteBundle {
-        texture: texture_assets.ziggurat.clone(),
-        sprite: Sprite {
-            anchor: bevy::sprite::Anchor::BottomCenter,
-            custom_size: Some(Vec2::new(
-                step_height * 11.0,
-                step_height * (step_count + 1) as f32,
-            )),
+    commands
+        .spawn(SpriteBundle {
+            texture: texture_assets.ziggurat.clone(),
+            sprite: Sprite {
+                anchor: bevy::sprite::Anchor::BottomCenter,
+                custom_size: Some(Vec2::new(
+                    step_height * 11.0,
+                    step_height * (step_count + 1) as f32,
+                )),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(Vec3::new(0.0, -0.05, 0.0)),
             ..Default::default()
-        },
-        transform: Transform::from_translation(Vec3::new(0.0, -0.05, 0.0)),
-        ..Default::default()
-    });
+        })
+        .insert(ArenaEntity);
 
     // Step colliders
     for i in 0..=step_count {
@@ -151,14 +179,16 @@ fn spawn_world(mut commands: Commands, texture_assets: Res<TextureAssets>) {
         }
         pos.y = 1.0 + step_height * i as f32;
 
-        commands.spawn(WorldBundle {
-            transform_bundle: TransformBundle {
-                local: Transform::from_translation(pos),
+        commands
+            .spawn(WorldBundle {
+                transform_bundle: TransformBundle {
+                    local: Transform::from_translation(pos),
+                    ..Default::default()
+                },
+                collider: Collider::rectangle(step_shape.x, step_shape.y),
                 ..Default::default()
-            },
-            collider: Collider::rectangle(step_shape.x, step_shape.y),
-            ..Default::default()
-        });
+            })
+            .insert(ArenaEntity);
 
         // Cliff sensor
         let cliff_shape = Vec2::new(step_shape.x + 1.5, 0.01);
@@ -185,6 +215,7 @@ fn spawn_world(mut commands: Commands, texture_assets: Res<TextureAssets>) {
                     PhysicsLayers::Lurker,
                     PhysicsLayers::PlayerProj,
                 ],
-            ));
+            ))
+            .insert(ArenaEntity);
     }
 }
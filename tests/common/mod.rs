@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+use last_stanza::headless::SimulationStats;
+use last_stanza::GamePlugin;
+use std::time::{Duration, Instant};
+
+/// Simulated time per update, a 60 fps frame
+pub const STEP: Duration = Duration::from_nanos(16_666_667);
+
+/// The headless game stepped until its assets have loaded and the first run
+/// has started
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(GamePlugin {
+        headless: true,
+        step: Some(STEP),
+    });
+
+    let started = Instant::now();
+    while app.world.resource::<SimulationStats>().runs == 0 {
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "the game never finished loading"
+        );
+        app.update();
+    }
+    app
+}
+
+/// Updates for `seconds` of simulated time
+pub fn run_for(app: &mut App, seconds: f32) {
+    let steps = (seconds / STEP.as_secs_f32()).ceil() as u32;
+    for _ in 0..steps {
+        app.update();
+    }
+}
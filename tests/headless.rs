@@ -0,0 +1,47 @@
+mod common;
+
+use bevy::prelude::*;
+use last_stanza::headless::SimulationStats;
+use last_stanza::score::RunStats;
+use std::time::Instant;
+
+/// Two minutes of play, which has to take far less than two minutes
+#[test]
+fn enemies_spawn_and_die() {
+    let mut app = common::headless_app();
+
+    let started = Instant::now();
+    common::run_for(&mut app, 120.0);
+    let stats = *app.world.resource::<SimulationStats>();
+
+    assert!(stats.spawned > 0, "nothing spawned: {stats:?}");
+    assert!(stats.died > 0, "nothing died: {stats:?}");
+    assert!(
+        app.world.resource::<Time<Real>>().elapsed_seconds() >= 120.0,
+        "the clock didn't follow the step"
+    );
+    assert!(
+        started.elapsed().as_secs_f32() < 120.0,
+        "ran no faster than real time"
+    );
+}
+
+/// The simulated player's input goes through the whole game: actions, weapons
+/// and the run's stats. It only shoots once there's an enemy to aim at
+#[test]
+fn simulated_player_fires() {
+    let mut app = common::headless_app();
+
+    let mut waited = 0.0;
+    while app.world.resource::<RunStats>().shots_fired == 0 {
+        assert!(waited < 60.0, "never fired in a minute of play");
+        common::run_for(&mut app, 1.0);
+        waited += 1.0;
+    }
+    let stats = app.world.resource::<RunStats>();
+
+    assert!(
+        stats.shots_hit <= stats.shots_fired,
+        "more hits than shots: {stats:?}"
+    );
+}